@@ -761,6 +761,11 @@ fn encode_project_path(path: &str) -> String {
     path.replace('/', "-").replace(' ', "-")
 }
 
+/// Directory holding Claude Code's session files for a project
+pub fn project_sessions_dir(project_path: &str) -> Option<PathBuf> {
+    Some(find_claude_data_dir()?.join("projects").join(encode_project_path(project_path)))
+}
+
 /// Store for the subagent filesystem watchers (one per project path)
 pub struct SubagentWatcherStore {
     active: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, SubagentWatcherHandle>>>,
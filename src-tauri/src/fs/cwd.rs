@@ -17,26 +17,46 @@ fn find_child_pid(parent_pid: u32) -> Option<u32> {
     None
 }
 
-#[tauri::command]
-pub fn get_terminal_cwd(session_id: String, state: tauri::State<AppState>) -> Result<String, String> {
+#[tauri::command(async)]
+pub fn get_terminal_cwd(session_id: String, state: tauri::State<'_, AppState>) -> Result<String, String> {
     let state_lock = state
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
 
-    let session = state_lock
-        .pty_sessions
-        .get(&session_id)
-        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    // Get the PID of the child process (shell or bwrap wrapper), which for
+    // persistent terminals lives in the session daemon
+    let (pid, backend, shell) = if let Some(session) = state_lock.pty_sessions.get(&session_id) {
+        (session.child.process_id(), session.sandbox_backend, &session.shell)
+    } else if let Some(attachment) = state_lock.daemon_sessions.get(&session_id) {
+        (attachment.pid, attachment.sandbox_backend, &attachment.shell)
+    } else {
+        return Err(format!("Session not found: {}", session_id));
+    };
 
-    // Get the PID of the child process (shell or bwrap wrapper)
-    let pid = session.child.process_id()
-        .ok_or_else(|| "Failed to get process ID".to_string())?;
+    // A shell with integration reports its cwd itself (OSC 7), which also
+    // works where /proc can't see the shell
+    let reported = shell.lock().ok().and_then(|t| t.cwd().map(String::from));
+    if let Some(cwd) = reported {
+        return Ok(match state_lock.workspace_context {
+            Some(ref ctx) => crate::workspace::manager::translate_path(&cwd, &ctx.path_map),
+            None => cwd,
+        });
+    }
+    // The local process is ssh; ask the host for its shell's cwd instead
+    if let Some(remote) = state_lock.remote_sessions.get(&session_id).cloned() {
+        drop(state_lock);
+        return crate::remote::ssh::remote_cwd(&remote);
+    }
+    let pid = pid.ok_or_else(|| "Failed to get process ID".to_string())?;
+    #[cfg(not(target_os = "linux"))]
+    let _ = backend;
 
     #[cfg(target_os = "linux")]
     {
-        // When sandboxed, the child PID is bwrap, not the shell.
+        // Under bwrap, the child PID is bwrap, not the shell.
         // Find the shell by looking for bwrap's child process.
-        let target_pid = if session.sandboxed {
+        // The Landlock launcher execs the shell in place, so its PID is the shell.
+        let target_pid = if backend == Some(crate::sandbox::SandboxBackendKind::Bwrap) {
             find_child_pid(pid).unwrap_or(pid)
         } else {
             pid
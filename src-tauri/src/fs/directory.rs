@@ -8,25 +8,32 @@ use walkdir::WalkDir;
 
 #[derive(Serialize, Clone, Debug)]
 pub struct DirectoryEntry {
-    name: String,
-    path: String,
-    is_dir: bool,
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
 }
 
 #[derive(Serialize, Clone, Debug)]
 pub struct RecursiveDirectoryEntry {
-    name: String,
-    path: String,
-    is_dir: bool,
-    depth: usize,
-    parent_path: String,
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub depth: usize,
+    pub parent_path: String,
 }
 
-#[tauri::command]
+/// List a directory. With the `session_id` of an ssh terminal the path is on
+/// the remote host and is read over SFTP.
+#[tauri::command(async)]
 pub fn read_directory(
     path: Option<String>,
-    state: tauri::State<AppState>,
+    session_id: Option<String>,
+    state: tauri::State<'_, AppState>,
 ) -> Result<Vec<DirectoryEntry>, String> {
+    if let Some(remote) = crate::remote::commands::find_remote(state.inner(), session_id.as_deref()) {
+        return crate::remote::commands::list_directory(&remote, path);
+    }
+
     let dir_path = if let Some(p) = path {
         PathBuf::from(p)
     } else {
@@ -98,13 +105,20 @@ pub fn write_file_content(path: String, content: String) -> Result<(), String> {
     fs::write(&path, &content).map_err(|e| format!("Failed to write file: {}", e))
 }
 
-#[tauri::command]
+/// Walk a directory tree, skipping `IGNORE_DIRS`. Like `read_directory`,
+/// `session_id` of an ssh terminal makes it walk the remote host instead.
+#[tauri::command(async)]
 pub fn read_directory_recursive(
     path: Option<String>,
     max_depth: Option<usize>,
     max_files: Option<usize>,
-    state: tauri::State<AppState>,
+    session_id: Option<String>,
+    state: tauri::State<'_, AppState>,
 ) -> Result<Vec<RecursiveDirectoryEntry>, String> {
+    if let Some(remote) = crate::remote::commands::find_remote(state.inner(), session_id.as_deref()) {
+        return crate::remote::commands::walk_directory(&remote, path, max_depth.unwrap_or(10), max_files.unwrap_or(10000));
+    }
+
     let root_path = if let Some(ref p) = path {
         PathBuf::from(p)
     } else {
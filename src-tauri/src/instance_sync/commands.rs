@@ -71,6 +71,11 @@ pub fn update_instance_state(
     store: State<'_, Arc<Mutex<InstanceSyncStore>>>,
 ) -> Result<InstanceState, String> {
     let store = store.lock().map_err(|e| e.to_string())?;
+    apply_update(&store, update)
+}
+
+/// Merge `update` into this instance's state file
+pub fn apply_update(store: &InstanceSyncStore, update: InstanceUpdate) -> Result<InstanceState, String> {
     let state_path = store.get_state_path();
 
     // Read current state
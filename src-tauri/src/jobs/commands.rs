@@ -0,0 +1,100 @@
+use crate::jobs::manager;
+use crate::jobs::types::{JobRecord, JobSpec};
+use crate::state::AppState;
+use tauri::AppHandle;
+
+/// Run a command in the background. Returns the job's initial record;
+/// `job-started`, `job-output` and `job-finished` events follow.
+#[tauri::command]
+pub fn submit_job(
+    spec: JobSpec,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<JobRecord, String> {
+    manager::submit(spec, app, state.inner())
+}
+
+/// Current record of a job, running or finished
+#[tauri::command]
+pub fn get_job(job_id: String, state: tauri::State<AppState>) -> Result<JobRecord, String> {
+    {
+        let st = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        if let Some(job) = st.jobs.get(&job_id) {
+            let mut record = job.record.clone();
+            if let Ok(out) = job.output.lock() {
+                record.output_bytes = out.total_written();
+                record.output_truncated = out.total_written() > manager::JOB_OUTPUT_BYTES as u64;
+            }
+            return Ok(record);
+        }
+    }
+    manager::read_record(&job_id)
+}
+
+/// Captured output of a job (the last `max_bytes`, bounded by what was kept)
+#[tauri::command]
+pub fn get_job_output(
+    job_id: String,
+    max_bytes: Option<usize>,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let bytes = {
+        let st = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        match st.jobs.get(&job_id) {
+            Some(job) => job
+                .output
+                .lock()
+                .map_err(|e| format!("Failed to lock job output: {}", e))?
+                .snapshot(max_bytes),
+            None => {
+                drop(st);
+                let mut bytes = manager::read_output(&job_id)?;
+                if let Some(max) = max_bytes.filter(|m| *m < bytes.len()) {
+                    bytes.drain(..bytes.len() - max);
+                }
+                bytes
+            }
+        }
+    };
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Kill a running job. Returns false if it had already finished.
+#[tauri::command]
+pub fn cancel_job(job_id: String, state: tauri::State<AppState>) -> Result<bool, String> {
+    manager::cancel(&job_id, state.inner())
+}
+
+/// Running and recent jobs, newest first, optionally only for one project
+#[tauri::command]
+pub fn list_jobs(
+    project_dir: Option<String>,
+    limit: Option<usize>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<JobRecord>, String> {
+    let running: Vec<JobRecord> = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .jobs
+        .values()
+        .map(|j| j.record.clone())
+        .collect();
+
+    // Running jobs also have a record on disk; the in-memory one wins
+    let mut jobs = running.clone();
+    jobs.extend(
+        manager::stored_records()
+            .into_iter()
+            .filter(|r| !running.iter().any(|j| j.id == r.id)),
+    );
+    if let Some(project_dir) = project_dir {
+        jobs.retain(|j| j.project_dir == project_dir);
+    }
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.started_at));
+    jobs.truncate(limit.unwrap_or(50));
+    Ok(jobs)
+}
@@ -0,0 +1,292 @@
+use crate::jobs::types::{JobRecord, JobSpec, JobStatus};
+use crate::pty::manager::{self, SpawnSpec};
+use crate::pty::scrollback::ScrollbackBuffer;
+use crate::sandbox::SandboxOptions;
+use crate::state::AppState;
+use portable_pty::ChildKiller;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter};
+
+/// Output kept per job, in memory while running and in `<id>.log` afterwards
+pub const JOB_OUTPUT_BYTES: usize = 256 * 1024;
+
+/// Oldest records beyond this are pruned when a new job is submitted
+const MAX_STORED_JOBS: usize = 200;
+
+/// Wide enough that CLIs don't wrap their output in the captured log
+const JOB_ROWS: u16 = 24;
+const JOB_COLS: u16 = 200;
+
+/// A job whose process is still alive
+pub struct RunningJob {
+    pub record: JobRecord,
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+    pub output: Arc<Mutex<ScrollbackBuffer>>,
+    pub cancelled: Arc<AtomicBool>,
+    pub timed_out: Arc<AtomicBool>,
+}
+
+pub fn jobs_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("jobs")
+}
+
+fn job_file(id: &str, ext: &str) -> Result<PathBuf, String> {
+    // Ids are uuids; anything else could point outside the jobs dir
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        return Err(format!("Invalid job id: {}", id));
+    }
+    Ok(jobs_dir().join(format!("{}.{}", id, ext)))
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+pub fn write_record(record: &JobRecord) -> Result<(), String> {
+    let dir = jobs_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let json = serde_json::to_string_pretty(record).map_err(|e| e.to_string())?;
+    std::fs::write(job_file(&record.id, "json")?, json)
+        .map_err(|e| format!("Failed to write job record: {}", e))
+}
+
+pub fn read_record(id: &str) -> Result<JobRecord, String> {
+    let content = std::fs::read_to_string(job_file(id, "json")?)
+        .map_err(|_| format!("Job not found: {}", id))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid job record: {}", e))
+}
+
+pub fn read_output(id: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(job_file(id, "log")?).map_err(|e| format!("Failed to read job output: {}", e))
+}
+
+/// All finished job records on disk, newest first
+pub fn stored_records() -> Vec<JobRecord> {
+    let Ok(entries) = std::fs::read_dir(jobs_dir()) else { return vec![] };
+    let mut records: Vec<JobRecord> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|c| serde_json::from_str(&c).ok())
+        .collect();
+    records.sort_by_key(|r: &JobRecord| std::cmp::Reverse(r.started_at));
+    records
+}
+
+/// Mark records left as running by an app that crashed or was killed. Jobs of
+/// another app instance that is still alive are left alone.
+pub fn mark_interrupted() {
+    let orphaned = |record: &JobRecord| match record.app_pid {
+        // Written before records carried the app's pid
+        None => true,
+        Some(pid) if pid == std::process::id() => false,
+        Some(pid) => {
+            let mut sys = System::new();
+            let pid = Pid::from_u32(pid);
+            sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), false, ProcessRefreshKind::nothing());
+            sys.process(pid).is_none()
+        }
+    };
+    for mut record in stored_records()
+        .into_iter()
+        .filter(|r| r.status == JobStatus::Running && orphaned(r))
+    {
+        eprintln!("[jobs] {} was still running when the app exited", record.id);
+        record.status = JobStatus::Interrupted;
+        if let Err(e) = write_record(&record) {
+            eprintln!("[jobs] {}", e);
+        }
+    }
+}
+
+fn prune_old_jobs() {
+    for record in stored_records().into_iter().skip(MAX_STORED_JOBS) {
+        for ext in ["json", "log"] {
+            if let Ok(path) = job_file(&record.id, ext) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Start a job in a hidden PTY. Returns its record once the process is up;
+/// `job-finished` follows when it exits, times out or is cancelled.
+pub fn submit(spec: JobSpec, app: AppHandle, state: &AppState) -> Result<JobRecord, String> {
+    if spec.command.trim().is_empty() {
+        return Err("Job command is empty".to_string());
+    }
+    if !std::path::Path::new(&spec.cwd).is_dir() {
+        return Err(format!("Job directory does not exist: {}", spec.cwd));
+    }
+    prune_old_jobs();
+
+    let project_dir = spec.project_dir.clone().unwrap_or_else(|| spec.cwd.clone());
+    let spawn_spec = SpawnSpec {
+        program: None,
+        args: vec!["-c".to_string(), spec.command.clone()],
+        env: spec.env.clone(),
+        login: Some(true),
+        cwd: Some(spec.cwd.clone()),
+        shell_integration: None,
+    };
+    let pty = manager::spawn_pty(
+        JOB_ROWS,
+        JOB_COLS,
+        spec.sandbox,
+        spec.sandbox_no_net,
+        Some(project_dir.clone()),
+        &spawn_spec,
+        &SandboxOptions::default(),
+    )?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let record = JobRecord {
+        id: id.clone(),
+        label: spec.label.clone(),
+        command: spec.command.clone(),
+        cwd: spec.cwd.clone(),
+        project_dir,
+        sandboxed: pty.sandboxed,
+        status: JobStatus::Running,
+        exit: None,
+        started_at: now_ms(),
+        finished_at: None,
+        duration_ms: None,
+        output_bytes: 0,
+        output_truncated: false,
+        app_pid: Some(std::process::id()),
+    };
+    eprintln!("[jobs] Started {}: {}", id, spec.command);
+
+    let mut reader = pty
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to clone reader: {}", e))?;
+    let running = RunningJob {
+        record: record.clone(),
+        killer: pty.child.clone_killer(),
+        output: Arc::new(Mutex::new(ScrollbackBuffer::new(JOB_OUTPUT_BYTES))),
+        cancelled: Arc::new(AtomicBool::new(false)),
+        timed_out: Arc::new(AtomicBool::new(false)),
+    };
+    let output = running.output.clone();
+    let cancelled = running.cancelled.clone();
+    let timed_out = running.timed_out.clone();
+
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .jobs
+        .insert(id.clone(), running);
+    let _ = write_record(&record);
+    let _ = app.emit("job-started", &record);
+
+    if let Some(secs) = spec.timeout_secs {
+        spawn_timeout(id.clone(), Duration::from_secs(secs), state.clone());
+    }
+
+    let state = state.clone();
+    let initial = record.clone();
+    std::thread::spawn(move || {
+        // Owning the session keeps the PTY master open until we're done reading
+        let mut pty = pty;
+        let mut buf = [0u8; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    if let Ok(mut out) = output.lock() {
+                        out.push(&buf[..n]);
+                    }
+                    let _ = app.emit(
+                        "job-output",
+                        serde_json::json!({
+                            "job_id": id,
+                            "data": String::from_utf8_lossy(&buf[..n]),
+                        }),
+                    );
+                }
+                // EOF, or EIO once the shell is gone
+                _ => break,
+            }
+        }
+
+        // Leave the running set before reaping so the timeout thread can't
+        // signal a recycled pid
+        let running = state.lock().ok().and_then(|mut st| st.jobs.remove(&id));
+        let exit = manager::reap_child(pty.child.as_mut());
+
+        let mut record = running.map(|r| r.record).unwrap_or(initial);
+        let finished_at = now_ms();
+        record.status = if cancelled.load(Ordering::SeqCst) {
+            JobStatus::Cancelled
+        } else if timed_out.load(Ordering::SeqCst) {
+            JobStatus::TimedOut
+        } else if exit.success {
+            JobStatus::Succeeded
+        } else {
+            JobStatus::Failed
+        };
+        record.exit = Some(exit);
+        record.finished_at = Some(finished_at);
+        record.duration_ms = Some(finished_at.saturating_sub(record.started_at));
+
+        if let Ok(out) = output.lock() {
+            record.output_bytes = out.total_written();
+            record.output_truncated = out.total_written() > JOB_OUTPUT_BYTES as u64;
+            if let Ok(path) = job_file(&id, "log") {
+                if let Err(e) = std::fs::write(&path, out.snapshot(None)) {
+                    eprintln!("[jobs] Failed to write {}: {}", path.display(), e);
+                }
+            }
+        }
+        if let Err(e) = write_record(&record) {
+            eprintln!("[jobs] {}", e);
+        }
+        eprintln!("[jobs] Finished {}: {:?}", id, record.status);
+        let _ = app.emit("job-finished", &record);
+    });
+
+    Ok(record)
+}
+
+fn spawn_timeout(id: String, timeout: Duration, state: AppState) {
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        if let Ok(mut st) = state.lock() {
+            if let Some(job) = st.jobs.get_mut(&id) {
+                eprintln!("[jobs] {} timed out after {:?}", id, timeout);
+                job.timed_out.store(true, Ordering::SeqCst);
+                let _ = job.killer.kill();
+            }
+        }
+    });
+}
+
+/// Kill a running job. Returns false if it had already finished.
+pub fn cancel(id: &str, state: &AppState) -> Result<bool, String> {
+    let mut st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    match st.jobs.get_mut(id) {
+        Some(job) => {
+            job.cancelled.store(true, Ordering::SeqCst);
+            job.killer
+                .kill()
+                .map_err(|e| format!("Failed to kill job: {}", e))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
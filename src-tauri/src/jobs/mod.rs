@@ -0,0 +1,14 @@
+//! Background jobs: non-interactive commands run in a hidden PTY with a
+//! timeout, captured output and a persisted record.
+//!
+//! - `types`: job spec, status and the record stored on disk
+//! - `manager`: spawning, timeouts, cancellation and persistence
+//! - `commands`: Tauri commands to submit, inspect, cancel and list jobs
+//!
+//! Records and output live in `~/.lirah/jobs/<id>.json` and `<id>.log`.
+
+pub mod commands;
+pub mod manager;
+pub mod types;
+
+pub use commands::{cancel_job, get_job, get_job_output, list_jobs, submit_job};
@@ -0,0 +1,55 @@
+use crate::pty::manager::ExitInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What to run. `command` goes through the user's login shell (`-lc`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct JobSpec {
+    pub command: String,
+    pub cwd: String,
+    pub env: HashMap<String, String>,
+    /// Kill the job after this many seconds
+    pub timeout_secs: Option<u64>,
+    pub sandbox: bool,
+    pub sandbox_no_net: bool,
+    /// Project the job belongs to, for `list_jobs`; defaults to `cwd`
+    pub project_dir: Option<String>,
+    /// Short human-readable name shown in job lists
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    TimedOut,
+    /// The app exited while the job was running
+    Interrupted,
+}
+
+/// Emitted in `job-started`/`job-finished` and persisted as `<id>.json`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobRecord {
+    pub id: String,
+    pub label: Option<String>,
+    pub command: String,
+    pub cwd: String,
+    pub project_dir: String,
+    pub sandboxed: bool,
+    pub status: JobStatus,
+    pub exit: Option<ExitInfo>,
+    /// Unix timestamps in milliseconds
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub duration_ms: Option<u64>,
+    /// Total bytes the job printed; only the tail is kept
+    pub output_bytes: u64,
+    pub output_truncated: bool,
+    /// Pid of the app process running the job, to spot records it left behind
+    #[serde(default)]
+    pub app_pid: Option<u32>,
+}
@@ -12,10 +12,27 @@ mod claude;
 mod opencode;
 mod workspace;
 mod fs_watcher;
+mod session_daemon;
+mod sandbox;
+mod jobs;
+mod triggers;
+mod mirror;
+mod remote;
+mod llm;
 
 use state::create_state;
-use pty::commands::{spawn_terminal, write_to_terminal, resize_terminal, close_terminal, spawn_hidden_terminal, start_commit_watcher, stop_commit_watcher, get_committable_files, run_git_command, generate_commit_message, generate_branch_tasks, generate_instance_sync_prompt, check_pty_child_process, kill_pty_child_process};
+use pty::commands::{spawn_terminal, write_to_terminal, resize_terminal, close_terminal, list_terminals, get_terminal_scrollback, start_commit_watcher, stop_commit_watcher, get_committable_files, run_git_command, generate_commit_message, generate_branch_tasks, generate_instance_sync_prompt, check_pty_child_process, kill_pty_child_process};
 use fs::{read_directory, get_terminal_cwd, read_file_content, write_file_content, read_directory_recursive, get_git_stats, get_current_branch, enable_file_watchers, disable_file_watchers, get_file_watchers_status, check_command_exists, get_git_diff, get_session_token_usage, get_project_stats, get_all_projects_stats, get_branch_completed_tasks, get_home_dir, set_file_executable, path_exists};
+use pty::recording::{start_terminal_recording, stop_terminal_recording, replay_recording, stop_replay};
+use pty::process_tree::get_terminal_process_tree;
+use pty::signals::signal_terminal_process;
+use pty::shell_integration::get_command_history;
+use pty::screen::get_terminal_screen;
+use pty::agent_state::get_agent_state;
+use pty::paste::paste_to_terminal;
+use pty::broadcast::{broadcast_to_terminals, broadcast_to_group, list_broadcast_groups, save_broadcast_group, remove_broadcast_group};
+use pty::output::{attach_terminal_output, ack_terminal_output};
+use pty::prompt_queue::{enqueue_prompt, list_prompt_queue, reorder_prompt_queue, cancel_queued_prompt};
 use typecheck::check_file_types;
 use python_parser::parse_python_skeleton;
 use instance_sync::{create_instance_sync_store, get_instance_id, register_instance, update_instance_state, get_all_instances, get_own_instance_state, unregister_instance, cleanup_stale_instances, start_instance_watcher};
@@ -23,6 +40,13 @@ use claude::{get_claude_data_paths, get_claude_sessions, get_claude_session, get
 use opencode::{get_opencode_data_paths, get_opencode_sessions, get_opencode_session, get_active_opencode_session};
 use workspace::{create_workspace, delete_workspace, list_workspaces, open_workspace, close_workspace};
 use fs_watcher::{start_fs_watcher, stop_fs_watcher, FsWatcherStore};
+use jobs::{submit_job, get_job, get_job_output, cancel_job, list_jobs};
+use triggers::{list_trigger_rules, save_trigger_rule, remove_trigger_rule};
+use mirror::{start_terminal_mirror, stop_terminal_mirror, list_terminal_mirrors};
+use remote::{list_ssh_profiles, save_ssh_profile, remove_ssh_profile, spawn_remote_terminal, read_remote_directory, read_remote_file};
+use llm::cancel_generation;
+use sandbox::{get_sandbox_policy, get_sandbox_backends, get_masked_secrets, get_terminal_resource_usage, list_overlays, list_overlay_changes, diff_overlay_change, apply_overlay_changes, discard_overlay_changes, delete_overlay};
+use session_daemon::{attach_persistent_session, detach_terminal, list_persistent_sessions};
 
 pub struct InitialPath(pub Option<String>);
 
@@ -31,6 +55,25 @@ fn get_initial_path(state: tauri::State<InitialPath>) -> Option<String> {
     state.0.clone()
 }
 
+/// Run the detached session daemon (`lirah --session-daemon`) instead of the UI
+#[cfg(unix)]
+pub fn run_session_daemon() {
+    if let Err(e) = session_daemon::server::run_daemon() {
+        eprintln!("[session-daemon] {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Confine ourselves with Landlock and exec the terminal's command
+/// (`lirah --landlock-exec`), see `sandbox::landlock`
+#[cfg(target_os = "linux")]
+pub fn run_landlock_exec() {
+    if let Err(e) = sandbox::landlock::run_launcher() {
+        eprintln!("[sandbox] {}", e);
+        std::process::exit(126);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run(initial_path: Option<String>) {
     // Disable GTK overlay scrolling to prevent scrollbars resizing on hover
@@ -50,6 +93,25 @@ pub fn run(initial_path: Option<String>) {
             write_to_terminal,
             resize_terminal,
             close_terminal,
+            list_terminals,
+            get_terminal_scrollback,
+            list_persistent_sessions,
+            attach_persistent_session,
+            detach_terminal,
+            start_terminal_recording,
+            stop_terminal_recording,
+            replay_recording,
+            stop_replay,
+            get_sandbox_policy,
+            get_sandbox_backends,
+            get_masked_secrets,
+            get_terminal_resource_usage,
+            list_overlays,
+            list_overlay_changes,
+            diff_overlay_change,
+            apply_overlay_changes,
+            discard_overlay_changes,
+            delete_overlay,
             read_directory,
             get_terminal_cwd,
             read_file_content,
@@ -67,7 +129,24 @@ pub fn run(initial_path: Option<String>) {
             get_project_stats,
             parse_python_skeleton,
             get_all_projects_stats,
-            spawn_hidden_terminal,
+            submit_job,
+            get_job,
+            get_job_output,
+            cancel_job,
+            list_jobs,
+            list_trigger_rules,
+            save_trigger_rule,
+            remove_trigger_rule,
+            start_terminal_mirror,
+            stop_terminal_mirror,
+            list_terminal_mirrors,
+            list_ssh_profiles,
+            save_ssh_profile,
+            remove_ssh_profile,
+            spawn_remote_terminal,
+            read_remote_directory,
+            read_remote_file,
+            cancel_generation,
             start_commit_watcher,
             stop_commit_watcher,
             get_committable_files,
@@ -104,11 +183,29 @@ pub fn run(initial_path: Option<String>) {
             path_exists,
             check_pty_child_process,
             kill_pty_child_process,
+            get_terminal_process_tree,
+            signal_terminal_process,
+            get_command_history,
+            get_terminal_screen,
+            get_agent_state,
+            paste_to_terminal,
+            broadcast_to_terminals,
+            broadcast_to_group,
+            list_broadcast_groups,
+            save_broadcast_group,
+            remove_broadcast_group,
+            attach_terminal_output,
+            ack_terminal_output,
+            enqueue_prompt,
+            list_prompt_queue,
+            reorder_prompt_queue,
+            cancel_queued_prompt,
             start_fs_watcher,
             stop_fs_watcher,
             get_initial_path
         ])
         .setup(|app| {
+            jobs::manager::mark_interrupted();
             start_instance_watcher(app.handle().clone());
             Ok(())
         })
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const TASK_COMMIT_MESSAGE: &str = "commit_message";
+pub const TASK_BRANCH_TASKS: &str = "branch_tasks";
+pub const TASK_INSTANCE_SYNC_PROMPT: &str = "instance_sync_prompt";
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// Claude Code (`claude --print`)
+    Claude,
+    /// `opencode run`
+    Opencode,
+    /// `codex exec`
+    Codex,
+    /// Gemini CLI
+    Gemini,
+    /// Any command that reads a prompt on stdin and answers on stdout
+    Template,
+}
+
+/// One step of a fallback chain
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Attempt {
+    pub backend: BackendKind,
+    /// Passed to the CLI's model flag; `None` leaves the CLI's default
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Template backend only: program and arguments, with `{model}` replaced
+    #[serde(default)]
+    pub command: Vec<String>,
+}
+
+impl Attempt {
+    fn new(backend: BackendKind, model: Option<&str>) -> Self {
+        Self {
+            backend,
+            model: model.map(String::from),
+            command: Vec::new(),
+        }
+    }
+}
+
+/// Overrides for one task
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TaskConfig {
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Chains keyed by the CLI the user picked (`claude`, `opencode`, ...)
+    #[serde(default)]
+    pub chains: HashMap<String, Vec<Attempt>>,
+}
+
+/// `~/.config/lirah/llm.json`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmConfig {
+    /// Per attempt, unless a task sets its own
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Chains for every task, keyed by CLI name; also how custom template
+    /// backends get a name the frontend can pick
+    #[serde(default)]
+    pub chains: HashMap<String, Vec<Attempt>>,
+    /// Keyed by task (`commit_message`, `branch_tasks`, `instance_sync_prompt`)
+    #[serde(default)]
+    pub tasks: HashMap<String, TaskConfig>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_timeout_secs(),
+            chains: HashMap::new(),
+            tasks: HashMap::new(),
+        }
+    }
+}
+
+/// Next to the sandbox policy, which sandboxes see read-only: template
+/// backends run their command unsandboxed
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".config")
+        .join("lirah")
+        .join("llm.json")
+}
+
+/// Where the config used to live, inside the home dir sandboxes could write
+fn legacy_config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("llm.json")
+}
+
+/// Read the config, falling back to the defaults if it is missing or invalid
+pub fn load() -> LlmConfig {
+    let path = config_path();
+    // Not read: a sandbox may have written it
+    let legacy = legacy_config_path();
+    if legacy.exists() && !path.exists() {
+        eprintln!(
+            "[llm] Ignoring {}; review it and move it to {} to keep using it",
+            legacy.display(),
+            path.display()
+        );
+    }
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return LlmConfig::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("[llm] Ignoring invalid {}: {}", path.display(), e);
+        LlmConfig::default()
+    })
+}
+
+/// What each CLI runs when nothing is configured; Claude Code for unknown names
+fn builtin_chain(cli: &str) -> Vec<Attempt> {
+    match cli {
+        // The free model first, then the paid one if it is unavailable
+        "opencode" => vec![
+            Attempt::new(BackendKind::Opencode, Some("opencode/glm-5-free")),
+            Attempt::new(BackendKind::Opencode, Some("opencode/minimax")),
+        ],
+        "codex" => vec![Attempt::new(BackendKind::Codex, None)],
+        "gemini" => vec![Attempt::new(BackendKind::Gemini, None)],
+        _ => vec![Attempt::new(BackendKind::Claude, Some("sonnet"))],
+    }
+}
+
+impl LlmConfig {
+    /// The chain to try for `task` when the user picked `cli`
+    pub fn chain(&self, task: &str, cli: &str) -> Result<Vec<Attempt>, String> {
+        self.tasks
+            .get(task)
+            .and_then(|t| t.chains.get(cli))
+            .or_else(|| self.chains.get(cli))
+            .cloned()
+            .map_or_else(|| Ok(builtin_chain(cli)), |chain| {
+                if chain.is_empty() {
+                    Err(format!("The LLM chain for '{}' is empty", cli))
+                } else {
+                    Ok(chain)
+                }
+            })
+    }
+
+    pub fn timeout(&self, task: &str) -> Duration {
+        let secs = self
+            .tasks
+            .get(task)
+            .and_then(|t| t.timeout_secs)
+            .unwrap_or(self.timeout_secs);
+        Duration::from_secs(secs)
+    }
+}
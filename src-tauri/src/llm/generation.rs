@@ -0,0 +1,136 @@
+use crate::llm::config::BackendKind;
+use crate::llm::runner::{LlmResponse, LlmRunner, RunControl, CANCELLED};
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+/// Generations still running, keyed by job id
+pub type Generations = HashMap<String, Arc<RunControl>>;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationStatus {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of `generation-finished`
+#[derive(Serialize, Clone, Debug)]
+pub struct GenerationFinished {
+    pub job_id: String,
+    pub task: String,
+    pub status: GenerationStatus,
+    /// What the command would have returned, on success
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Who answered, when one did
+    pub backend: Option<BackendKind>,
+    pub model: Option<String>,
+}
+
+/// Run `prompt` through `runner` in the background and return a job id right
+/// away. Output streams as `generation-progress` events; `finish` turns the
+/// answer into the result carried by `generation-finished`.
+pub fn start<F>(app: AppHandle, state: &AppState, runner: LlmRunner, prompt: String, finish: F) -> Result<String, String>
+where
+    F: FnOnce(LlmResponse) -> Result<serde_json::Value, String> + Send + 'static,
+{
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let control = Arc::new(RunControl::default());
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .generations
+        .insert(job_id.clone(), control.clone());
+
+    let state = state.clone();
+    let id = job_id.clone();
+    std::thread::spawn(move || {
+        let task = runner.task().to_string();
+        let outcome = runner.run_streaming(&prompt, &control, |attempt, chunk| {
+            let _ = app.emit(
+                "generation-progress",
+                serde_json::json!({
+                    "job_id": id,
+                    "task": task,
+                    "chunk": chunk,
+                    "backend": attempt.backend,
+                    "model": attempt.model,
+                }),
+            );
+        });
+
+        let mut finished = GenerationFinished {
+            job_id: id.clone(),
+            task,
+            status: GenerationStatus::Failed,
+            result: None,
+            error: None,
+            backend: None,
+            model: None,
+        };
+        match outcome {
+            Ok(response) => {
+                finished.backend = Some(response.backend);
+                finished.model = response.model.clone();
+                match finish(response) {
+                    Ok(result) => {
+                        finished.status = GenerationStatus::Succeeded;
+                        finished.result = Some(result);
+                    }
+                    Err(e) => finished.error = Some(e),
+                }
+            }
+            Err(e) if e == CANCELLED => finished.status = GenerationStatus::Cancelled,
+            Err(e) => finished.error = Some(e),
+        }
+
+        if let Ok(mut st) = state.lock() {
+            st.generations.remove(&id);
+        }
+        eprintln!("[llm] Generation {} ({}) finished: {:?}", id, finished.task, finished.status);
+        let _ = app.emit("generation-finished", &finished);
+    });
+    Ok(job_id)
+}
+
+/// Report a result that needed no model call under a job id, so callers
+/// handle it like any other generation
+pub fn completed(app: &AppHandle, task: &str, result: serde_json::Value) -> Result<String, String> {
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let _ = app.emit(
+        "generation-finished",
+        GenerationFinished {
+            job_id: job_id.clone(),
+            task: task.to_string(),
+            status: GenerationStatus::Succeeded,
+            result: Some(result),
+            error: None,
+            backend: None,
+            model: None,
+        },
+    );
+    Ok(job_id)
+}
+
+/// Stop a running generation and every process its CLI started. Returns
+/// false if it had already finished.
+#[tauri::command]
+pub fn cancel_generation(job_id: String, state: tauri::State<AppState>) -> Result<bool, String> {
+    let control = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .generations
+        .get(&job_id)
+        .cloned();
+    match control {
+        Some(control) => {
+            control.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,16 @@
+//! One-shot LLM calls (commit messages, branch tasks, sync prompts) through
+//! whichever coding-agent CLI the user has installed.
+//!
+//! - `config`: backends, per-task model/fallback chains and timeouts
+//! - `runner`: `LlmRunner`, which walks a chain until a backend answers
+//! - `generation`: background runs with streamed output and cancellation
+//!
+//! The prompt always goes to the CLI on stdin. Chains can be overridden in
+//! `~/.config/lirah/llm.json`; without it the built-in defaults apply.
+
+pub mod config;
+pub mod generation;
+pub mod runner;
+
+pub use generation::cancel_generation;
+pub use runner::LlmRunner;
@@ -0,0 +1,321 @@
+use crate::llm::config::{self, Attempt, BackendKind};
+use crate::pty::output::Utf8Decoder;
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to keep collecting output after the CLI exits. Something it
+/// started may hold the pipes open, so the readers can't be waited on.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Error returned when a run was stopped through its `RunControl`
+pub const CANCELLED: &str = "Cancelled";
+
+/// The answer and who gave it
+#[derive(Serialize, Clone, Debug)]
+pub struct LlmResponse {
+    pub text: String,
+    pub backend: BackendKind,
+    /// `None` when the CLI's default model answered
+    pub model: Option<String>,
+}
+
+/// Lets another thread stop a run, including everything the CLI spawned
+#[derive(Default)]
+pub struct RunControl {
+    cancelled: AtomicBool,
+    /// Process group of the CLI currently running, while it is unreaped
+    pgid: Mutex<Option<u32>>,
+}
+
+impl RunControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Ok(pgid) = self.pgid.lock() {
+            if let Some(pgid) = *pgid {
+                kill_group(pgid);
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// SIGKILL a whole process group; CLIs like opencode leave workers behind
+/// if only the main process dies
+fn kill_group(pgid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+/// Runs a one-shot prompt through a task's fallback chain
+pub struct LlmRunner {
+    task: String,
+    chain: Vec<Attempt>,
+    timeout: Duration,
+    cwd: PathBuf,
+}
+
+impl Attempt {
+    /// Program and arguments; the prompt itself goes on stdin
+    fn argv(&self) -> Result<Vec<String>, String> {
+        let model = self.model.clone();
+        let with_model = |mut argv: Vec<String>, flag: &str| {
+            if let Some(model) = &model {
+                argv.extend([flag.to_string(), model.clone()]);
+            }
+            argv
+        };
+        Ok(match self.backend {
+            BackendKind::Claude => with_model(vec!["claude".into(), "--print".into()], "--model"),
+            BackendKind::Opencode => with_model(vec!["opencode".into(), "run".into()], "-m"),
+            // `-` makes codex read the prompt from stdin
+            BackendKind::Codex => {
+                let mut argv = with_model(vec!["codex".into(), "exec".into()], "-m");
+                argv.push("-".into());
+                argv
+            }
+            BackendKind::Gemini => with_model(vec!["gemini".into()], "-m"),
+            BackendKind::Template => {
+                if self.command.is_empty() {
+                    return Err("Template backend has no command".to_string());
+                }
+                self.command
+                    .iter()
+                    .map(|arg| arg.replace("{model}", model.as_deref().unwrap_or_default()))
+                    .collect()
+            }
+        })
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        let _ = tx.send(buf);
+    });
+    rx
+}
+
+/// Forward a pipe's output in chunks as it arrives
+fn read_chunks(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+impl LlmRunner {
+    /// Runner for `task` using the chain configured for the CLI the user picked
+    pub fn for_task(task: &str, cli: &str, cwd: &str) -> Result<Self, String> {
+        let config = config::load();
+        Ok(Self {
+            task: task.to_string(),
+            chain: config.chain(task, cli)?,
+            timeout: config.timeout(task),
+            cwd: PathBuf::from(cwd),
+        })
+    }
+
+    /// Use `timeout` per attempt instead of the configured one
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+
+    pub fn task(&self) -> &str {
+        &self.task
+    }
+
+    /// Try each backend in turn until one answers with non-empty output,
+    /// handing stdout to `on_chunk` as it arrives. A cancelled run fails with
+    /// `CANCELLED` without trying the rest of the chain.
+    pub fn run_streaming(
+        &self,
+        prompt: &str,
+        control: &RunControl,
+        mut on_chunk: impl FnMut(&Attempt, &str),
+    ) -> Result<LlmResponse, String> {
+        let mut last_error = String::new();
+        for attempt in &self.chain {
+            if control.is_cancelled() {
+                return Err(CANCELLED.to_string());
+            }
+            let model = attempt.model.as_deref().unwrap_or("default model");
+            match self.run_attempt(attempt, prompt, control, &mut on_chunk) {
+                Ok(text) => {
+                    eprintln!("[llm] {} answered by {:?} ({})", self.task, attempt.backend, model);
+                    return Ok(LlmResponse {
+                        text,
+                        backend: attempt.backend,
+                        model: attempt.model.clone(),
+                    });
+                }
+                Err(_) if control.is_cancelled() => return Err(CANCELLED.to_string()),
+                Err(e) => {
+                    eprintln!("[llm] {} via {:?} ({}) failed: {}", self.task, attempt.backend, model, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(format!("LLM command failed: {}", last_error))
+    }
+
+    fn run_attempt(
+        &self,
+        attempt: &Attempt,
+        prompt: &str,
+        control: &RunControl,
+        on_chunk: &mut impl FnMut(&Attempt, &str),
+    ) -> Result<String, String> {
+        let command = attempt
+            .argv()?
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // A login shell picks up the user's PATH; exec keeps the CLI as the
+        // leader of its own process group so one kill stops all of it
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+        let mut cmd = Command::new(&shell);
+        cmd.args(["-lc", &format!("exec {}", command)])
+            .current_dir(&self.cwd)
+            .env("TERM", "xterm-256color")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("Failed to run LLM command: {}", e))?;
+        if let Ok(mut pgid) = control.pgid.lock() {
+            *pgid = Some(child.id());
+        }
+        // Cancelled between the check before this attempt and the spawn
+        if control.is_cancelled() {
+            kill_group(child.id());
+        }
+
+        // Feed stdin from its own thread so a CLI that writes before it has
+        // read everything can't deadlock against us. Never joined: it ends
+        // when the last holder of the pipe exits or closes it.
+        let stdin = child.stdin.take();
+        let prompt = prompt.to_string();
+        std::thread::spawn(move || {
+            if let Some(mut stdin) = stdin {
+                let _ = stdin.write_all(prompt.as_bytes());
+            }
+        });
+        let chunks = child.stdout.take().map(read_chunks);
+        let stderr = child.stderr.take().map(read_to_end);
+
+        let mut decoder = Utf8Decoder::default();
+        let mut stdout = String::new();
+        let mut on_bytes = |bytes: &[u8]| {
+            let text = decoder.decode(bytes);
+            if !text.is_empty() {
+                on_chunk(attempt, &text);
+                stdout.push_str(&text);
+            }
+        };
+
+        // On a timeout or cancel, leave the readers behind: anything the CLI
+        // spawned may still hold the pipes open
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match chunks.as_ref().map(|rx| rx.recv_timeout(POLL_INTERVAL)) {
+                Some(Ok(bytes)) => {
+                    on_bytes(&bytes);
+                    continue;
+                }
+                Some(Err(RecvTimeoutError::Timeout)) => {}
+                Some(Err(RecvTimeoutError::Disconnected)) | None => std::thread::sleep(POLL_INTERVAL),
+            }
+            let finished = {
+                // Reap under the lock so a cancel never signals a recycled pgid
+                let mut pgid = control.pgid.lock().map_err(|e| format!("Failed to lock run control: {}", e))?;
+                let status = child
+                    .try_wait()
+                    .map_err(|e| format!("Failed to wait for LLM command: {}", e))?;
+                if status.is_some() {
+                    *pgid = None;
+                }
+                status
+            };
+            if let Some(status) = finished {
+                break status;
+            }
+            if control.is_cancelled() {
+                kill_group(child.id());
+                let _ = child.wait();
+                return Err(CANCELLED.to_string());
+            }
+            if Instant::now() >= deadline {
+                kill_group(child.id());
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", self.timeout.as_secs()));
+            }
+        };
+
+        // Whatever was still buffered when the CLI exited
+        let drain_deadline = Instant::now() + DRAIN_TIMEOUT;
+        if let Some(rx) = &chunks {
+            while let Some(left) = drain_deadline.checked_duration_since(Instant::now()) {
+                let Ok(bytes) = rx.recv_timeout(left) else { break };
+                on_bytes(&bytes);
+            }
+        }
+        let rest = decoder.finish();
+        if !rest.is_empty() {
+            on_chunk(attempt, &rest);
+            stdout.push_str(&rest);
+        }
+        let stdout = stdout.trim().to_string();
+
+        if !status.success() {
+            let wait = drain_deadline.saturating_duration_since(Instant::now()).max(POLL_INTERVAL);
+            let stderr = stderr.and_then(|rx| rx.recv_timeout(wait).ok()).unwrap_or_default();
+            let stderr = String::from_utf8_lossy(&stderr).trim().to_string();
+            return Err(if !stdout.is_empty() {
+                stdout
+            } else if !stderr.is_empty() {
+                stderr
+            } else {
+                format!("exited with {}", status)
+            });
+        }
+        if stdout.is_empty() {
+            return Err("no output".to_string());
+        }
+        Ok(stdout)
+    }
+}
@@ -2,6 +2,17 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    #[cfg(unix)]
+    if std::env::args().nth(1).as_deref() == Some("--session-daemon") {
+        nevo_terminal_lib::run_session_daemon();
+        return;
+    }
+    #[cfg(target_os = "linux")]
+    if std::env::args().nth(1).as_deref() == Some("--landlock-exec") {
+        nevo_terminal_lib::run_landlock_exec();
+        return;
+    }
+
     let initial_path = std::env::args().nth(1).and_then(|p| {
         let path = std::path::Path::new(&p);
         if path.is_dir() {
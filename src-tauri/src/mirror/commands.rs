@@ -0,0 +1,66 @@
+use crate::mirror::server;
+use crate::mirror::types::{MirrorInfo, Share, DEFAULT_PORT};
+use crate::state::AppState;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::Arc;
+
+/// Make a terminal watchable over the local mirror server, starting the
+/// server on the first call (on `port`, default 47681; ignored once it runs).
+/// Sharing an already shared terminal returns its existing URL.
+#[tauri::command]
+pub fn start_terminal_mirror(
+    session_id: String,
+    port: Option<u16>,
+    state: tauri::State<AppState>,
+) -> Result<MirrorInfo, String> {
+    let mut st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if !st.pty_sessions.contains_key(&session_id) && !st.daemon_sessions.contains_key(&session_id) {
+        return Err(format!("Session not found: {}", session_id));
+    }
+
+    let port = match st.mirror.port {
+        Some(port) => port,
+        None => {
+            let port = server::start(state.inner().clone(), port.unwrap_or(DEFAULT_PORT))?;
+            st.mirror.port = Some(port);
+            port
+        }
+    };
+
+    let share = st.mirror.shares.entry(session_id.clone()).or_insert_with(|| Share {
+        token: format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple()),
+        revoked: Arc::new(AtomicBool::new(false)),
+        viewers: Arc::new(AtomicUsize::new(0)),
+    });
+    Ok(MirrorInfo::new(port, &session_id, share))
+}
+
+/// Stop mirroring a terminal and disconnect its viewers. The token stops
+/// working; sharing again issues a new one. Returns false if it wasn't shared.
+#[tauri::command]
+pub fn stop_terminal_mirror(session_id: String, state: tauri::State<AppState>) -> Result<bool, String> {
+    Ok(state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .mirror
+        .remove_session(&session_id))
+}
+
+/// Terminals currently shared, with their URLs and viewer counts
+#[tauri::command]
+pub fn list_terminal_mirrors(state: tauri::State<AppState>) -> Result<Vec<MirrorInfo>, String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let Some(port) = st.mirror.port else { return Ok(vec![]) };
+    let mut mirrors: Vec<MirrorInfo> = st
+        .mirror
+        .shares
+        .iter()
+        .map(|(session_id, share)| MirrorInfo::new(port, session_id, share))
+        .collect();
+    mirrors.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+    Ok(mirrors)
+}
@@ -0,0 +1,17 @@
+//! Read-only web mirror of terminals: an opt-in WebSocket server on
+//! 127.0.0.1 that lets someone on the same machine (or over an SSH port
+//! forward) watch a session live.
+//!
+//! - `types`: shares, their tokens and the server's state
+//! - `server`: listener, handshake/token check and the per-viewer stream
+//! - `commands`: Tauri commands to start, stop and list mirrors
+//!
+//! A viewer connects to `ws://127.0.0.1:<port>/terminals/<session_id>?token=<token>`
+//! and receives the scrollback as the first text message, then the live
+//! output. Anything the viewer sends is ignored.
+
+pub mod commands;
+pub mod server;
+pub mod types;
+
+pub use commands::{list_terminal_mirrors, start_terminal_mirror, stop_terminal_mirror};
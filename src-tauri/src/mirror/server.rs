@@ -0,0 +1,242 @@
+use crate::pty::output::OutputSink;
+use crate::pty::scrollback::ScrollbackBuffer;
+use crate::session_daemon::{client as daemon_client, types::{DaemonRequest, DaemonResponse}};
+use crate::state::AppState;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::{StatusCode, Uri};
+use tungstenite::{Error as WsError, Message};
+
+/// A client that connects but never finishes the handshake is dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for output before checking on the viewer
+const FRAME_WAIT: Duration = Duration::from_millis(50);
+
+/// Read timeout while polling the viewer for close frames
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A viewer that can't take a frame within this long is disconnected
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAX_VIEWERS: usize = 8;
+
+/// Where a terminal's history is read from
+enum History {
+    Local(Arc<Mutex<ScrollbackBuffer>>),
+    Daemon,
+}
+
+/// Everything a viewer with a valid token needs
+struct Grant {
+    session_id: String,
+    sink: Arc<OutputSink>,
+    history: History,
+    revoked: Arc<AtomicBool>,
+    /// Held for as long as the viewer is connected, released if the
+    /// handshake fails after authorizing
+    _slot: ViewerSlot,
+}
+
+/// Counts a viewer while it is connected
+struct ViewerSlot(Arc<AtomicUsize>);
+
+impl ViewerSlot {
+    /// Take one of the share's viewer slots, if any is left. Reserving in one
+    /// step keeps concurrent handshakes from overshooting the cap.
+    fn reserve(viewers: &Arc<AtomicUsize>) -> Option<Self> {
+        viewers
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < MAX_VIEWERS).then_some(n + 1))
+            .ok()
+            .map(|_| ViewerSlot(viewers.clone()))
+    }
+}
+
+impl Drop for ViewerSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Listen on 127.0.0.1 and serve viewers until the app exits. Returns the
+/// port actually bound (`port` 0 picks a free one).
+pub fn start(state: AppState, port: u16) -> Result<u16, String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to listen on 127.0.0.1:{}: {}", port, e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read listener address: {}", e))?
+        .port();
+    eprintln!("[mirror] Listening on 127.0.0.1:{}", port);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let state = state.clone();
+            thread::spawn(move || {
+                if let Err(e) = serve(stream, &state) {
+                    eprintln!("[mirror] {}", e);
+                }
+            });
+        }
+    });
+    Ok(port)
+}
+
+/// Compare without returning early, so response timing doesn't leak the token
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Check `/terminals/<session_id>?token=<token>` against the shared terminals
+fn authorize(state: &AppState, uri: &Uri) -> Result<Grant, (StatusCode, &'static str)> {
+    let session_id = uri
+        .path()
+        .strip_prefix("/terminals/")
+        .filter(|id| !id.is_empty() && !id.contains('/'))
+        .ok_or((StatusCode::NOT_FOUND, "Not found"))?;
+    let token = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .unwrap_or_default();
+
+    let st = state
+        .lock()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Internal error"))?;
+    // Unknown terminals and wrong tokens look the same from outside
+    let share = st
+        .mirror
+        .shares
+        .get(session_id)
+        .filter(|share| token_matches(token, &share.token))
+        .ok_or((StatusCode::NOT_FOUND, "Not found"))?;
+    let (sink, history) = if let Some(session) = st.pty_sessions.get(session_id) {
+        (session.output.clone(), History::Local(session.scrollback.clone()))
+    } else if let Some(attachment) = st.daemon_sessions.get(session_id) {
+        (attachment.output.clone(), History::Daemon)
+    } else {
+        return Err((StatusCode::NOT_FOUND, "Not found"));
+    };
+    let slot = ViewerSlot::reserve(&share.viewers)
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, "Too many viewers"))?;
+
+    Ok(Grant {
+        session_id: session_id.to_string(),
+        sink,
+        history,
+        revoked: share.revoked.clone(),
+        _slot: slot,
+    })
+}
+
+fn reject(status: StatusCode, message: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(message.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+fn read_history(grant: &Grant) -> Result<String, String> {
+    match &grant.history {
+        History::Local(scrollback) => {
+            let bytes = scrollback
+                .lock()
+                .map_err(|e| format!("Failed to lock scrollback: {}", e))?
+                .snapshot(None);
+            Ok(String::from_utf8_lossy(&bytes).to_string())
+        }
+        History::Daemon => match daemon_client::request(&DaemonRequest::Scrollback {
+            session_id: grant.session_id.clone(),
+            max_bytes: None,
+        })? {
+            DaemonResponse::Scrollback { data } => Ok(data),
+            other => Err(format!("Unexpected daemon response: {:?}", other)),
+        },
+    }
+}
+
+/// Handle one connection: authorize the handshake, send the scrollback, then
+/// stream output until the viewer leaves, the share is stopped or the
+/// terminal ends
+// The handshake callback's error type is fixed by tungstenite
+#[allow(clippy::result_large_err)]
+fn serve(stream: TcpStream, state: &AppState) -> Result<(), String> {
+    let peer = stream
+        .peer_addr()
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)))
+        .map_err(|e| format!("Failed to configure socket: {}", e))?;
+
+    let mut grant = None;
+    let mut socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        match authorize(state, request.uri()) {
+            Ok(g) => {
+                grant = Some(g);
+                Ok(response)
+            }
+            Err((status, message)) => Err(reject(status, message)),
+        }
+    })
+    .map_err(|e| format!("Rejected {}: {}", peer, e))?;
+    let Some(grant) = grant else { return Ok(()) };
+
+    eprintln!("[mirror] {} is watching {}", peer, grant.session_id);
+
+    // Subscribe before reading the history so nothing falls in between; at
+    // worst a frame shows up in both
+    let frames = grant.sink.subscribe();
+    let history = read_history(&grant)?;
+    socket
+        .send(Message::text(history))
+        .map_err(|e| format!("Failed to send scrollback to {}: {}", peer, e))?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| format!("Failed to configure socket: {}", e))?;
+
+    loop {
+        if grant.revoked.load(Ordering::SeqCst) {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+            break;
+        }
+        match frames.recv_timeout(FRAME_WAIT) {
+            Ok(data) => socket
+                .send(Message::text(data))
+                .map_err(|e| format!("Dropped {}: {}", peer, e))?,
+            Err(RecvTimeoutError::Timeout) => {}
+            // The terminal is gone, or this viewer fell too far behind
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = socket.close(None);
+                let _ = socket.flush();
+                break;
+            }
+        }
+        // Read-only: whatever the viewer sends is discarded; reading just
+        // answers pings and notices when it goes away
+        match socket.read() {
+            Ok(_) => {}
+            Err(WsError::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(WsError::ConnectionClosed) | Err(WsError::AlreadyClosed) => break,
+            Err(e) => return Err(format!("Dropped {}: {}", peer, e)),
+        }
+    }
+
+    eprintln!("[mirror] {} stopped watching {}", peer, grant.session_id);
+    Ok(())
+}
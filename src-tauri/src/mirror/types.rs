@@ -0,0 +1,66 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Port the mirror server listens on unless another is requested
+pub const DEFAULT_PORT: u16 = 47681;
+
+/// A terminal opened up for viewing
+pub struct Share {
+    pub token: String,
+    /// Set when the share is stopped so connected viewers hang up
+    pub revoked: Arc<AtomicBool>,
+    /// Viewers connected right now
+    pub viewers: Arc<AtomicUsize>,
+}
+
+impl Share {
+    pub fn revoke(&self) {
+        self.revoked.store(true, Ordering::SeqCst);
+    }
+}
+
+#[derive(Default)]
+pub struct MirrorState {
+    /// Port the server is listening on, once started
+    pub port: Option<u16>,
+    /// Shared terminals, keyed by session id
+    pub shares: HashMap<String, Share>,
+}
+
+impl MirrorState {
+    /// Stop mirroring a terminal, e.g. because it was closed
+    pub fn remove_session(&mut self, session_id: &str) -> bool {
+        match self.shares.remove(session_id) {
+            Some(share) => {
+                share.revoke();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// What the frontend gets back for a shared terminal
+#[derive(Serialize, Clone, Debug)]
+pub struct MirrorInfo {
+    pub session_id: String,
+    pub token: String,
+    pub url: String,
+    pub viewers: usize,
+}
+
+impl MirrorInfo {
+    pub fn new(port: u16, session_id: &str, share: &Share) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            token: share.token.clone(),
+            url: format!(
+                "ws://127.0.0.1:{}/terminals/{}?token={}",
+                port, session_id, share.token
+            ),
+            viewers: share.viewers.load(Ordering::Relaxed),
+        }
+    }
+}
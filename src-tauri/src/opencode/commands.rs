@@ -13,7 +13,7 @@ const OPENCODE_DATA_ENV: &str = "OPENCODE_DATA_DIR";
 const OPENCODE_DATA_LOCATIONS: &[&str] = &[".local/share/opencode"];
 
 /// Find the OpenCode data directory
-fn find_opencode_data_dir() -> Option<PathBuf> {
+pub fn find_opencode_data_dir() -> Option<PathBuf> {
     // 1. Check environment variable override first
     if let Ok(env_path) = std::env::var(OPENCODE_DATA_ENV) {
         let path = PathBuf::from(env_path);
@@ -0,0 +1,431 @@
+use crate::instance_sync::commands::{apply_update, InstanceSyncStore};
+use crate::instance_sync::types::{InstanceStatus, InstanceUpdate};
+use crate::pty::process_tree::descendants;
+use crate::pty::prompt_queue;
+use crate::pty::screen::{self, ScreenModel};
+use crate::state::AppState;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Output within this window counts as the agent still drawing
+const OUTPUT_ACTIVE_MS: u64 = 1500;
+
+/// A session file written within this window counts as the agent working
+const SESSION_FILE_ACTIVE_MS: u64 = 3000;
+
+/// CPU use of the agent and its children above which it is working
+const CPU_BUSY_PERCENT: f32 = 15.0;
+
+/// Waiting for input this long without any activity becomes idle
+const IDLE_AFTER_MS: u64 = 5 * 60 * 1000;
+
+/// Only the bottom of the screen holds the prompt or spinner
+const SCREEN_TAIL_LINES: usize = 15;
+
+/// Quiet time required before a queued prompt is pasted
+const PROMPT_QUIET_MS: u64 = 2000;
+
+/// Deliver the next queued prompt even if the agent never looked busy after
+/// the previous one (e.g. a slash command that answered instantly)
+const REDELIVER_AFTER_MS: u64 = 10_000;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentState {
+    Working,
+    WaitingForInput,
+    WaitingForPermission,
+    Idle,
+    Exited,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentKind {
+    Claude,
+    Opencode,
+}
+
+/// What the agent in a terminal is doing, as sent in `agent-state-changed`
+#[derive(Serialize, Clone, Debug)]
+pub struct AgentStatus {
+    pub session_id: String,
+    pub agent: AgentKind,
+    pub pid: u32,
+    pub state: AgentState,
+    /// Unix timestamp (milliseconds) of the last state change
+    pub since: u64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Called by a terminal's reader for every chunk of output
+pub fn mark_output(last_output: &AtomicU64) {
+    last_output.store(now_ms(), Ordering::Relaxed);
+}
+
+fn permission_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)do you want to (proceed|make this edit|create|allow)|permission (required|needed)|\ballow (once|always)\b")
+            .expect("permission pattern is valid")
+    })
+}
+
+fn working_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // Claude Code: "(esc to interrupt)" next to the spinner; opencode: "esc interrupt"
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)esc to interrupt|\besc interrupt\b|ctrl\+c to interrupt")
+            .expect("working pattern is valid")
+    })
+}
+
+/// Whether the screen shows the agent's input box
+fn shows_input_prompt(agent: AgentKind, screen: &str) -> bool {
+    static CLAUDE: OnceLock<Regex> = OnceLock::new();
+    match agent {
+        // "> " at the start of a line, inside the input box border or not
+        AgentKind::Claude => CLAUDE
+            .get_or_init(|| Regex::new(r"(?m)^\s*│?\s*[>❯](?:\s|$)").expect("prompt pattern is valid"))
+            .is_match(screen),
+        // opencode draws its input area with a heavy left border
+        AgentKind::Opencode => screen.contains('┃'),
+    }
+}
+
+fn agent_kind(process: &Process) -> Option<AgentKind> {
+    let matches = |needle: &str, package: &str| {
+        process.name().to_string_lossy().eq_ignore_ascii_case(needle)
+            || process.cmd().iter().take(2).any(|arg| {
+                let arg = arg.to_string_lossy();
+                arg.contains(package)
+                    || Path::new(arg.as_ref()).file_name().is_some_and(|f| f == needle)
+            })
+    };
+    if matches("claude", "@anthropic-ai/claude-code") {
+        Some(AgentKind::Claude)
+    } else if matches("opencode", "opencode-ai") {
+        Some(AgentKind::Opencode)
+    } else {
+        None
+    }
+}
+
+/// Topmost agent process under the terminal, with its CPU use including children
+fn find_agent(sys: &System, root: Pid) -> Option<(AgentKind, &Process, f32)> {
+    let mut pids = descendants(sys, root);
+    pids.insert(0, root);
+    let (kind, process) = pids
+        .iter()
+        .filter_map(|pid| sys.process(*pid))
+        .find_map(|p| agent_kind(p).map(|kind| (kind, p)))?;
+    let cpu = std::iter::once(process.pid())
+        .chain(descendants(sys, process.pid()))
+        .filter_map(|pid| sys.process(pid))
+        .map(|p| p.cpu_usage())
+        .sum();
+    Some((kind, process, cpu))
+}
+
+fn newest_mtime_ms(dir: &Path) -> Option<u64> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .chain(std::fs::metadata(dir).ok().and_then(|m| m.modified().ok()))
+        .max()
+        .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64)
+}
+
+/// When the agent last wrote its session data
+fn session_file_activity(kind: AgentKind, cwd: Option<&Path>) -> Option<u64> {
+    match kind {
+        AgentKind::Claude => {
+            let dir = crate::claude::commands::project_sessions_dir(&cwd?.to_string_lossy())?;
+            newest_mtime_ms(&dir)
+        }
+        AgentKind::Opencode => {
+            let storage = crate::opencode::commands::find_opencode_data_dir()?.join("storage");
+            ["message", "part", "session"]
+                .iter()
+                .filter_map(|d| std::fs::metadata(storage.join(d)).ok()?.modified().ok())
+                .max()
+                .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64)
+        }
+    }
+}
+
+fn screen_tail(screen: &ScreenModel) -> String {
+    let Ok(snapshot) = screen::snapshot(screen, false) else { return String::new() };
+    let lines: Vec<&String> = snapshot.lines.iter().filter(|l| !l.is_empty()).collect();
+    let start = lines.len().saturating_sub(SCREEN_TAIL_LINES);
+    lines[start..].iter().map(|l| l.as_str()).collect::<Vec<_>>().join("\n")
+}
+
+struct Signals {
+    screen: String,
+    since_output_ms: u64,
+    since_session_file_ms: u64,
+    cpu: f32,
+}
+
+fn classify(s: &Signals) -> AgentState {
+    if permission_pattern().is_match(&s.screen) {
+        AgentState::WaitingForPermission
+    } else if working_pattern().is_match(&s.screen)
+        || s.cpu >= CPU_BUSY_PERCENT
+        || (s.since_output_ms < OUTPUT_ACTIVE_MS && s.since_session_file_ms < SESSION_FILE_ACTIVE_MS)
+    {
+        AgentState::Working
+    } else if s.since_output_ms.min(s.since_session_file_ms) >= IDLE_AFTER_MS {
+        AgentState::Idle
+    } else {
+        AgentState::WaitingForInput
+    }
+}
+
+/// Fast transitions are reported at once; the rest must hold for two polls
+/// so a brief pause between tool calls doesn't flap the state
+fn is_urgent(state: AgentState) -> bool {
+    matches!(state, AgentState::WaitingForPermission | AgentState::Exited)
+}
+
+/// Busy if any terminal's agent is working, active if one is waiting on the
+/// user, idle otherwise
+fn instance_status(state: &AppState) -> InstanceStatus {
+    let Ok(st) = state.lock() else { return InstanceStatus::Idle };
+    let states: Vec<AgentState> = st.agent_states.values().map(|s| s.state).collect();
+    if states.contains(&AgentState::Working) {
+        InstanceStatus::Busy
+    } else if states.iter().any(|s| matches!(s, AgentState::WaitingForInput | AgentState::WaitingForPermission)) {
+        InstanceStatus::Active
+    } else {
+        InstanceStatus::Idle
+    }
+}
+
+fn sync_instance(app: &AppHandle, state: &AppState) {
+    let Some(store) = app.try_state::<Arc<Mutex<InstanceSyncStore>>>() else { return };
+    let status = instance_status(state);
+    let Ok(store) = store.lock() else { return };
+    // Not registered yet (no project open) is fine; the next change retries
+    let _ = apply_update(
+        &store,
+        InstanceUpdate {
+            project_path: None,
+            current_focus: None,
+            active_files: None,
+            claude_session_id: None,
+            opencode_session_id: None,
+            status: Some(status),
+        },
+    );
+}
+
+/// What the detector reads from a session on every poll
+struct Probe {
+    root: Pid,
+    screen: ScreenModel,
+    last_output: Arc<AtomicU64>,
+}
+
+fn probe(state: &AppState, session_id: &str) -> Option<Probe> {
+    let st = state.lock().ok()?;
+    let (pid, screen, last_output) = if let Some(session) = st.pty_sessions.get(session_id) {
+        (session.child.process_id(), session.screen.clone(), session.last_output.clone())
+    } else {
+        let attachment = st.daemon_sessions.get(session_id)?;
+        (attachment.pid, attachment.screen.clone(), attachment.last_output.clone())
+    };
+    Some(Probe { root: Pid::from_u32(pid?), screen, last_output })
+}
+
+fn publish(app: &AppHandle, state: &AppState, status: &AgentStatus, previous: Option<AgentState>) {
+    eprintln!("[agent] {} {:?}: {:?} -> {:?}", status.session_id, status.agent, previous, status.state);
+    if let Ok(mut st) = state.lock() {
+        st.agent_states.insert(status.session_id.clone(), status.clone());
+    }
+    let _ = app.emit(
+        "agent-state-changed",
+        serde_json::json!({
+            "session_id": status.session_id,
+            "agent": status.agent,
+            "pid": status.pid,
+            "state": status.state,
+            "previous": previous,
+            "since": status.since,
+        }),
+    );
+    sync_instance(app, state);
+}
+
+/// Detection state of one terminal, carried between polls
+#[derive(Default)]
+struct Detector {
+    current: Option<AgentStatus>,
+    /// A non-urgent state seen once, reported if the next poll agrees
+    pending: Option<AgentState>,
+    last_delivery: u64,
+}
+
+impl Detector {
+    fn poll(&mut self, app: &AppHandle, state: &AppState, session_id: &str, sys: &System, probe: &Probe) {
+        let now = now_ms();
+        let observed = match find_agent(sys, probe.root) {
+            Some((kind, process, cpu)) => {
+                let activity = session_file_activity(kind, process.cwd()).unwrap_or(0);
+                let signals = Signals {
+                    screen: screen_tail(&probe.screen),
+                    since_output_ms: now.saturating_sub(probe.last_output.load(Ordering::Relaxed)),
+                    since_session_file_ms: now.saturating_sub(activity),
+                    cpu,
+                };
+                let state = classify(&signals);
+                Some((kind, process.pid().as_u32(), state, Some(signals)))
+            }
+            None => self
+                .current
+                .as_ref()
+                .filter(|c| c.state != AgentState::Exited)
+                .map(|c| (c.agent, c.pid, AgentState::Exited, None)),
+        };
+        let Some((agent, pid, observed, signals)) = observed else { return };
+
+        let unchanged = self
+            .current
+            .as_ref()
+            .is_some_and(|c| c.state == observed && c.pid == pid);
+        if unchanged {
+            self.pending = None;
+        } else if self.current.is_none() || is_urgent(observed) || self.pending == Some(observed) {
+            self.pending = None;
+            let previous = self.current.as_ref().map(|c| c.state);
+            let status = AgentStatus {
+                session_id: session_id.to_string(),
+                agent,
+                pid,
+                state: observed,
+                since: now,
+            };
+            publish(app, state, &status, previous);
+            self.current = Some(status);
+        } else {
+            self.pending = Some(observed);
+        }
+
+        // Hand the next queued prompt to an agent sitting at its input
+        // prompt, once per return to it (or after a while, for prompts
+        // that never made it busy)
+        if let (Some(status), Some(signals)) = (&self.current, &signals) {
+            let ready = status.state == AgentState::WaitingForInput
+                && signals.since_output_ms >= PROMPT_QUIET_MS
+                && shows_input_prompt(status.agent, &signals.screen)
+                && (status.since > self.last_delivery || now.saturating_sub(self.last_delivery) >= REDELIVER_AFTER_MS);
+            if ready && prompt_queue::deliver_next(app, state, session_id) {
+                self.last_delivery = now_ms();
+            }
+        }
+    }
+
+    /// The terminal is gone; an agent still marked running went with it
+    fn finish(self, app: &AppHandle, state: &AppState, session_id: &str) {
+        let Some(mut status) = self.current else { return };
+        if status.state != AgentState::Exited {
+            let previous = Some(status.state);
+            status.state = AgentState::Exited;
+            status.since = now_ms();
+            publish(app, state, &status, previous);
+        }
+        if let Ok(mut st) = state.lock() {
+            st.agent_states.remove(session_id);
+        }
+        sync_instance(app, state);
+    }
+}
+
+/// Terminals newly handed to the sampler thread, which starts with the first
+static WATCH_REQUESTS: OnceLock<Mutex<Sender<String>>> = OnceLock::new();
+
+/// Watch a terminal for a Claude Code or opencode process and report its
+/// state until the terminal goes away
+pub fn watch_terminal(app: AppHandle, session_id: String, state: AppState) {
+    let requests = WATCH_REQUESTS.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        spawn_sampler(app, state, rx);
+        Mutex::new(tx)
+    });
+    if let Ok(tx) = requests.lock() {
+        let _ = tx.send(session_id);
+    }
+}
+
+/// One thread for all terminals, so each poll scans the process table once
+/// however many terminals are open
+fn spawn_sampler(app: AppHandle, state: AppState, requests: Receiver<String>) {
+    std::thread::spawn(move || {
+        let refresh = ProcessRefreshKind::nothing()
+            .with_cpu()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_cwd(UpdateKind::OnlyIfNotSet);
+        let mut sys = System::new();
+        let mut detectors: HashMap<String, Detector> = HashMap::new();
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            for session_id in requests.try_iter() {
+                detectors.entry(session_id).or_default();
+            }
+            if detectors.is_empty() {
+                continue;
+            }
+
+            let probes: Vec<(String, Option<Probe>)> = detectors
+                .keys()
+                .map(|id| (id.clone(), probe(&state, id)))
+                .collect();
+            if probes.iter().any(|(_, p)| p.is_some()) {
+                sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+            }
+
+            for (session_id, probe) in probes {
+                match probe {
+                    Some(probe) => {
+                        if let Some(detector) = detectors.get_mut(&session_id) {
+                            detector.poll(&app, &state, &session_id, &sys, &probe);
+                        }
+                    }
+                    None => {
+                        if let Some(detector) = detectors.remove(&session_id) {
+                            detector.finish(&app, &state, &session_id);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Last detected agent state of a terminal, if an agent has run in it
+#[tauri::command]
+pub fn get_agent_state(session_id: String, state: tauri::State<AppState>) -> Result<Option<AgentStatus>, String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    Ok(st.agent_states.get(&session_id).cloned())
+}
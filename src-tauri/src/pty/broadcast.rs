@@ -0,0 +1,186 @@
+use crate::pty::agent_state::AgentState;
+use crate::pty::commands::write_input;
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+
+/// Terminals without an agent count as idle after this much silence
+const SHELL_QUIET_MS: u64 = 2000;
+
+/// A named set of terminals that receive the same input
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BroadcastGroup {
+    /// Assigned when the group is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub session_ids: Vec<String>,
+    /// Skip terminals whose agent is busy (or whose shell is still printing)
+    #[serde(default)]
+    pub only_idle: bool,
+}
+
+pub type BroadcastGroups = HashMap<String, BroadcastGroup>;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BroadcastDelivery {
+    pub session_id: String,
+    pub status: DeliveryStatus,
+    /// Why the terminal was skipped or the write failed
+    pub reason: Option<String>,
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Why a terminal isn't ready for input, if it isn't
+fn busy_reason(state: &AppState, session_id: &str) -> Option<String> {
+    let st = state.lock().ok()?;
+    if let Some(status) = st.agent_states.get(session_id) {
+        return match status.state {
+            AgentState::Working => Some("agent is working".to_string()),
+            AgentState::WaitingForPermission => Some("agent is waiting for permission".to_string()),
+            AgentState::WaitingForInput | AgentState::Idle | AgentState::Exited => None,
+        };
+    }
+    let last_output = if let Some(session) = st.pty_sessions.get(session_id) {
+        session.last_output.load(Ordering::Relaxed)
+    } else {
+        st.daemon_sessions.get(session_id)?.last_output.load(Ordering::Relaxed)
+    };
+    (now_ms().saturating_sub(last_output) < SHELL_QUIET_MS).then(|| "terminal is busy".to_string())
+}
+
+/// Write `data` to every terminal concurrently, so one stuck PTY doesn't
+/// hold up the rest
+fn deliver(state: &AppState, session_ids: &[String], data: &str, only_idle: bool) -> Vec<BroadcastDelivery> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = session_ids
+            .iter()
+            .map(|session_id| {
+                scope.spawn(move || {
+                    if only_idle {
+                        if let Some(reason) = busy_reason(state, session_id) {
+                            return BroadcastDelivery {
+                                session_id: session_id.clone(),
+                                status: DeliveryStatus::Skipped,
+                                reason: Some(reason),
+                            };
+                        }
+                    }
+                    match write_input(state, session_id.clone(), data.to_string()) {
+                        Ok(()) => BroadcastDelivery {
+                            session_id: session_id.clone(),
+                            status: DeliveryStatus::Sent,
+                            reason: None,
+                        },
+                        Err(e) => BroadcastDelivery {
+                            session_id: session_id.clone(),
+                            status: DeliveryStatus::Failed,
+                            reason: Some(e),
+                        },
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .zip(session_ids)
+            .map(|(handle, session_id)| {
+                handle.join().unwrap_or_else(|_| BroadcastDelivery {
+                    session_id: session_id.clone(),
+                    status: DeliveryStatus::Failed,
+                    reason: Some("Write thread panicked".to_string()),
+                })
+            })
+            .collect()
+    })
+}
+
+/// Send the same input to several terminals. Returns one entry per terminal;
+/// a failure in one doesn't stop the others.
+#[tauri::command(async)]
+pub fn broadcast_to_terminals(
+    session_ids: Vec<String>,
+    data: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<BroadcastDelivery>, String> {
+    Ok(deliver(state.inner(), &session_ids, &data, false))
+}
+
+/// Send input to every terminal in a group, honouring its `only_idle` setting
+#[tauri::command(async)]
+pub fn broadcast_to_group(
+    group_id: String,
+    data: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<BroadcastDelivery>, String> {
+    let group = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .broadcast_groups
+        .get(&group_id)
+        .cloned()
+        .ok_or_else(|| format!("Broadcast group not found: {}", group_id))?;
+    Ok(deliver(state.inner(), &group.session_ids, &data, group.only_idle))
+}
+
+#[tauri::command]
+pub fn list_broadcast_groups(state: tauri::State<AppState>) -> Result<Vec<BroadcastGroup>, String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let mut groups: Vec<BroadcastGroup> = st.broadcast_groups.values().cloned().collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(groups)
+}
+
+/// Create a group (empty `id`) or replace an existing one
+#[tauri::command]
+pub fn save_broadcast_group(
+    mut group: BroadcastGroup,
+    state: tauri::State<AppState>,
+) -> Result<BroadcastGroup, String> {
+    if group.id.is_empty() {
+        group.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut seen = HashSet::new();
+    group.session_ids.retain(|id| seen.insert(id.clone()));
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .broadcast_groups
+        .insert(group.id.clone(), group.clone());
+    Ok(group)
+}
+
+/// Delete a group. Returns false if there was none with that id.
+#[tauri::command]
+pub fn remove_broadcast_group(group_id: String, state: tauri::State<AppState>) -> Result<bool, String> {
+    Ok(state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .broadcast_groups
+        .remove(&group_id)
+        .is_some())
+}
+
+/// Take a closed terminal out of every group
+pub fn remove_session(groups: &mut BroadcastGroups, session_id: &str) {
+    for group in groups.values_mut() {
+        group.session_ids.retain(|id| id != session_id);
+    }
+}
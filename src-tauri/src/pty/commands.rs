@@ -1,30 +1,90 @@
 use crate::commit_watcher::CommitWatcherStore;
-use crate::pty::manager;
-use crate::state::AppState;
+use crate::llm::config::{TASK_BRANCH_TASKS, TASK_COMMIT_MESSAGE, TASK_INSTANCE_SYNC_PROMPT};
+use crate::llm::{generation, LlmRunner};
+use crate::pty::output::{self, OutputFrame};
+use crate::pty::{agent_state, broadcast, manager, process_tree, prompt_queue, screen, shell_integration, signals};
+use crate::remote::types::RemoteSession;
+use crate::sandbox::limits;
+use crate::sandbox::{secrets, SandboxOptions};
+use crate::session_daemon::{client as daemon_client, types::DaemonRequest};
+use crate::state::{AppState, AppStateData};
+use crate::triggers::engine::SessionMatcher;
 use std::io::Read;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::thread;
+use std::time::Duration;
 use sysinfo::{Pid, ProcessesToUpdate, System};
-use tauri::{AppHandle, Emitter};
+use tauri::ipc::{Channel, JavaScriptChannelId};
+use tauri::{AppHandle, Emitter, Webview};
 use uuid::Uuid;
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_terminal(
     rows: u16,
     cols: u16,
     sandbox: bool,
     sandbox_no_net: bool,
     project_dir: Option<String>,
+    persistent: Option<bool>,
+    spec: Option<manager::SpawnSpec>,
+    sandbox_options: Option<SandboxOptions>,
+    on_output: Option<JavaScriptChannelId>,
+    webview: Webview,
     app: AppHandle,
     state: tauri::State<AppState>,
+) -> Result<serde_json::Value, String> {
+    let spec = spec.unwrap_or_default();
+    // Output frames go to this channel; without one, to `terminal-output` events
+    let on_output = on_output.map(|id| id.channel_on(webview));
+    let sandbox_options = sandbox_options.unwrap_or_default();
+
+    // Persistent sessions live in the session daemon and survive app restarts
+    if persistent.unwrap_or(false) {
+        return crate::session_daemon::commands::spawn_persistent_terminal(
+            rows, cols, sandbox, sandbox_no_net, project_dir, spec, sandbox_options, on_output, app, state.inner(),
+        );
+    }
+
+    spawn_local_terminal(
+        rows, cols, sandbox, sandbox_no_net, project_dir, spec, sandbox_options, on_output, None, app, state.inner(),
+    )
+}
+
+/// Spawn a terminal owned by this process. `remote` is registered together
+/// with the session, before any output is read, so the sidebar and cleanup
+/// on an early exit both see it as an ssh terminal.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_local_terminal(
+    rows: u16,
+    cols: u16,
+    sandbox: bool,
+    sandbox_no_net: bool,
+    project_dir: Option<String>,
+    spec: manager::SpawnSpec,
+    sandbox_options: SandboxOptions,
+    on_output: Option<Channel<OutputFrame>>,
+    remote: Option<RemoteSession>,
+    app: AppHandle,
+    state: &AppState,
 ) -> Result<serde_json::Value, String> {
     // Generate a unique session ID
     let session_id = Uuid::new_v4().to_string();
 
     // Spawn the PTY
-    let session = manager::spawn_pty(rows, cols, sandbox, sandbox_no_net, project_dir)?;
+    let session = manager::spawn_pty(rows, cols, sandbox, sandbox_no_net, project_dir.clone(), &spec, &sandbox_options)?;
     let actually_sandboxed = session.sandboxed;
+    let sandbox_backend = session.sandbox_backend;
+    let overlay_id = session.overlay_id.clone();
+    let masked_count = session.masked_secrets.len();
+    let limit_enforcement = session.limit_enforcement;
+    if let Some(scope) = session.limit_scope.clone() {
+        limits::spawn_monitor(app.clone(), session_id.clone(), scope, session.shutdown.clone());
+    }
+    if sandbox {
+        secrets::record_masked(&session_id, project_dir.as_deref(), actually_sandboxed, &session.masked_secrets);
+    }
 
     // Clone the master for the reader thread
     let mut reader = session
@@ -32,12 +92,52 @@ pub fn spawn_terminal(
         .try_clone_reader()
         .map_err(|e| format!("Failed to clone reader: {}", e))?;
 
-    // Clone shutdown flag for the reader thread
+    // Clone shutdown flag and scrollback for the reader thread
     let shutdown_flag = session.shutdown.clone();
+    let scrollback = session.scrollback.clone();
+    let recorder = session.recorder.clone();
+    let shell = session.shell.clone();
+    let screen = session.screen.clone();
+    let last_output = session.last_output.clone();
+    let triggers = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .triggers
+        .clone();
+    let mut matcher = SessionMatcher::new(&session_id);
+
+    // Decoding, triggers, recording and delivery to the webview happen on the
+    // output pipeline's thread, in batches
+    let sink = session.output.clone();
+    if let Some(channel) = on_output {
+        sink.attach(channel, true);
+    }
+    let pipeline_app = app.clone();
+    let (output_tx, pipeline) = output::spawn_pipeline(app.clone(), session_id.clone(), sink.clone(), move |data| {
+        matcher.observe(&pipeline_app, &triggers, data);
+
+        // Tee into the asciicast recording, if one is running
+        if let Ok(mut rec) = recorder.lock() {
+            if let Some(rec) = rec.as_mut() {
+                rec.output(data);
+            }
+        }
+    });
+
+    // Store the session before the reader starts, so a shell that exits
+    // straight away still finds it to reap
+    {
+        let mut st = state.lock().map_err(|e| format!("Failed to lock state: {}", e))?;
+        st.pty_sessions.insert(session_id.clone(), session);
+        if let Some(remote) = remote {
+            st.remote_sessions.insert(session_id.clone(), remote);
+        }
+    }
 
-    // Spawn a thread to read from PTY and emit events
+    // Spawn a thread to read from PTY and feed the pipeline
     let session_id_clone = session_id.clone();
     let app_clone = app.clone();
+    let state_inner = state.clone();
     thread::spawn(move || {
         let mut buf = [0u8; 8192];
         loop {
@@ -53,34 +153,39 @@ pub fn spawn_terminal(
                         break;
                     }
 
-                    // Convert bytes to string (handling UTF-8)
-                    let data = String::from_utf8_lossy(&buf[..n]).to_string();
-
-                    // Emit event to frontend
-                    let _ = app_clone.emit(
-                        "terminal-output",
-                        serde_json::json!({
-                            "session_id": session_id_clone,
-                            "data": data,
-                        }),
-                    );
+                    // Keep raw bytes so a reattached frontend can redraw the history
+                    if let Ok(mut sb) = scrollback.lock() {
+                        sb.push(&buf[..n]);
+                    }
+                    screen::feed(&screen, &buf[..n]);
+                    agent_state::mark_output(&last_output);
+
+                    // Track cwd and finished commands from OSC 7/133 markers
+                    shell_integration::observe(&app_clone, &session_id_clone, &shell, &buf[..n]);
+
+                    // Blocks while the webview is behind, which in turn stalls the program
+                    if output_tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
                 }
                 Ok(_) => {
-                    // EOF reached, process exited
+                    // EOF reached, process exited. Flush pending output first so
+                    // the exit notice comes after it.
                     if !shutdown_flag.load(Ordering::SeqCst) {
-                        let _ = app_clone.emit(
-                            "terminal-output",
-                            serde_json::json!({
-                                "session_id": session_id_clone,
-                                "data": "\r\n[Process exited]\r\n",
-                            }),
-                        );
+                        drop(output_tx);
+                        let _ = pipeline.join();
+                        sink.send(&app_clone, &session_id_clone, "\r\n[Process exited]\r\n".to_string());
+                        reap_exited_session(&app_clone, &state_inner, &session_id_clone);
                     }
                     break;
                 }
                 Err(e) => {
+                    // Linux reports EIO on the master once the slave side closes
                     if !shutdown_flag.load(Ordering::SeqCst) {
                         eprintln!("Error reading from PTY: {}", e);
+                        drop(output_tx);
+                        let _ = pipeline.join();
+                        reap_exited_session(&app_clone, &state_inner, &session_id_clone);
                     }
                     break;
                 }
@@ -88,35 +193,146 @@ pub fn spawn_terminal(
         }
     });
 
-    // Store the session
-    state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?
-        .pty_sessions
-        .insert(session_id.clone(), session);
+    agent_state::watch_terminal(app, session_id.clone(), state.clone());
 
     Ok(serde_json::json!({
         "session_id": session_id,
         "sandboxed": actually_sandboxed,
+        "sandbox_backend": sandbox_backend,
+        "overlay_id": overlay_id,
+        "masked_secrets": masked_count,
+        "limits": limit_enforcement,
     }))
 }
 
+/// Payload of the `terminal-exited` event
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TerminalExitedPayload {
+    pub session_id: String,
+    #[serde(flatten)]
+    pub exit: manager::ExitInfo,
+}
+
+/// Drop everything kept for a session besides its PTY: session triggers,
+/// queued prompts, broadcast group membership, mirror shares and remote state.
+/// Used both when a terminal is closed and when its shell exits on its own.
+pub fn cleanup_session(st: &mut AppStateData, session_id: &str) {
+    if let Ok(mut triggers) = st.triggers.lock() {
+        triggers.remove_session(session_id);
+    }
+    prompt_queue::remove_session(&mut st.prompt_queues, session_id);
+    broadcast::remove_session(&mut st.broadcast_groups, session_id);
+    st.mirror.remove_session(session_id);
+    if let Some(remote) = st.remote_sessions.remove(session_id) {
+        crate::remote::ssh::cleanup(remote);
+    }
+}
+
+/// Remove a session whose shell has exited and report how it ended
+fn reap_exited_session(app: &AppHandle, state: &AppState, session_id: &str) {
+    let session = match state.lock() {
+        Ok(mut st) => {
+            let session = st.pty_sessions.remove(session_id);
+            if session.is_some() {
+                cleanup_session(&mut st, session_id);
+            }
+            session
+        }
+        Err(_) => None,
+    };
+    // Already removed by close_terminal
+    let Some(mut session) = session else { return };
+    // Stops the limit monitor along with the reader
+    session.shutdown.store(true, Ordering::SeqCst);
+
+    let exit = manager::reap_child(session.child.as_mut());
+    if let Some(rec) = session.recorder.lock().ok().and_then(|mut r| r.take()) {
+        let _ = rec.finish();
+    }
+    eprintln!("[pty] Session {} exited: {:?}", session_id, exit);
+
+    let _ = app.emit(
+        "terminal-exited",
+        TerminalExitedPayload {
+            session_id: session_id.to_string(),
+            exit,
+        },
+    );
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TerminalInfo {
+    pub session_id: String,
+    pub pid: Option<u32>,
+    /// "running", "closing" (shutdown requested) or "persistent" (owned by the session daemon)
+    pub state: String,
+    pub sandboxed: bool,
+    pub uptime_secs: u64,
+}
+
+/// List every terminal this window knows about
+#[tauri::command]
+pub fn list_terminals(state: tauri::State<AppState>) -> Result<Vec<TerminalInfo>, String> {
+    let state_lock = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut terminals: Vec<TerminalInfo> = state_lock
+        .pty_sessions
+        .iter()
+        .map(|(id, session)| TerminalInfo {
+            session_id: id.clone(),
+            pid: session.child.process_id(),
+            state: if session.shutdown.load(Ordering::SeqCst) { "closing" } else { "running" }
+                .to_string(),
+            sandboxed: session.sandboxed,
+            uptime_secs: session.started_at.elapsed().as_secs(),
+        })
+        .collect();
+
+    terminals.extend(state_lock.daemon_sessions.iter().map(|(id, attachment)| TerminalInfo {
+        session_id: id.clone(),
+        pid: attachment.pid,
+        state: "persistent".to_string(),
+        sandboxed: attachment.sandboxed,
+        uptime_secs: now.saturating_sub(attachment.created_at),
+    }));
+
+    terminals.sort_by_key(|t| std::cmp::Reverse(t.uptime_secs));
+    Ok(terminals)
+}
+
 #[tauri::command]
 pub fn write_to_terminal(
     session_id: String,
     data: String,
     state: tauri::State<AppState>,
 ) -> Result<(), String> {
-    let mut state_lock = state
+    write_input(state.inner(), session_id, data)
+}
+
+/// Send input to a local or persistent terminal
+pub fn write_input(state: &AppState, session_id: String, data: String) -> Result<(), String> {
+    let state_lock = state
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
 
-    let session = state_lock
-        .pty_sessions
-        .get_mut(&session_id)
-        .ok_or_else(|| format!("Session not found: {}", session_id))?;
-
-    manager::write_to_pty(session, &data)
+    // Write outside the state lock; a full PTY buffer must not stall other commands
+    if let Some(session) = state_lock.pty_sessions.get(&session_id) {
+        let writer = session.writer.clone();
+        drop(state_lock);
+        return manager::write_to_pty(&writer, &data);
+    }
+    if state_lock.daemon_sessions.contains_key(&session_id) {
+        drop(state_lock);
+        return daemon_client::send(&DaemonRequest::Write { session_id, data });
+    }
+    Err(format!("Session not found: {}", session_id))
 }
 
 #[tauri::command]
@@ -130,12 +346,20 @@ pub fn resize_terminal(
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
 
-    let session = state_lock
-        .pty_sessions
-        .get_mut(&session_id)
-        .ok_or_else(|| format!("Session not found: {}", session_id))?;
-
-    manager::resize_pty(session, rows, cols)
+    if let Some(session) = state_lock.pty_sessions.get_mut(&session_id) {
+        if let Ok(mut rec) = session.recorder.lock() {
+            if let Some(rec) = rec.as_mut() {
+                rec.resize(cols, rows);
+            }
+        }
+        return manager::resize_pty(session, rows, cols);
+    }
+    if let Some(attachment) = state_lock.daemon_sessions.get(&session_id) {
+        screen::resize(&attachment.screen, rows, cols);
+        drop(state_lock);
+        return daemon_client::send(&DaemonRequest::Resize { session_id, rows, cols });
+    }
+    Err(format!("Session not found: {}", session_id))
 }
 
 #[tauri::command]
@@ -148,6 +372,8 @@ pub fn close_terminal(
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
 
+    cleanup_session(&mut state_lock, &session_id);
+
     if let Some(mut session) = state_lock.pty_sessions.remove(&session_id) {
         // Signal reader thread to stop
         session.shutdown.store(true, Ordering::SeqCst);
@@ -155,129 +381,78 @@ pub fn close_terminal(
         // Kill child process
         let _ = session.child.kill();
 
+        // Flush any recording still in progress
+        if let Some(rec) = session.recorder.lock().ok().and_then(|mut r| r.take()) {
+            let _ = rec.finish();
+        }
+
         // Emit closed event
         let _ = app.emit(
             "terminal-closed",
             serde_json::json!({"session_id": session_id}),
         );
+    } else if let Some(attachment) = state_lock.daemon_sessions.remove(&session_id) {
+        // Closing a persistent terminal ends it for good; use detach_terminal to keep it
+        drop(state_lock);
+        attachment.detach();
+        daemon_client::send(&DaemonRequest::Kill { session_id: session_id.clone() })?;
+        let _ = app.emit(
+            "terminal-closed",
+            serde_json::json!({"session_id": session_id}),
+        );
     }
 
     Ok(())
 }
 
+/// Return the buffered output of a running session so a remounted terminal can
+/// replay it before subscribing to live `terminal-output` events again.
 #[tauri::command]
-pub fn spawn_hidden_terminal(
-    project_dir: String,
-    command: String,
-    app: AppHandle,
+pub fn get_terminal_scrollback(
+    session_id: String,
+    max_bytes: Option<usize>,
     state: tauri::State<AppState>,
-) -> Result<String, String> {
-    use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
-    use std::sync::atomic::AtomicBool;
-    use std::sync::Arc;
-
-    let session_id = Uuid::new_v4().to_string();
-    eprintln!("[hidden-terminal] Spawning: {} in {}", command, project_dir);
-
-    let pty_system = NativePtySystem::default();
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|e| format!("Failed to open PTY: {}", e))?;
-
-    // Parse the command: run via shell -c
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-    let mut cmd = CommandBuilder::new(&shell);
-    cmd.args(&["-lc", &command]);
-    cmd.env("TERM", "xterm-256color");
-    cmd.cwd(&project_dir);
-
-    let child = pty_pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|e| format!("Failed to spawn hidden terminal: {}", e))?;
-
-    let master = pty_pair.master;
-    let writer = master
-        .take_writer()
-        .map_err(|e| format!("Failed to take writer: {}", e))?;
-
-    let mut reader = master
-        .try_clone_reader()
-        .map_err(|e| format!("Failed to clone reader: {}", e))?;
-
-    let shutdown = Arc::new(AtomicBool::new(false));
-    let shutdown_flag = shutdown.clone();
-
-    let session_id_clone = session_id.clone();
-    let app_clone = app.clone();
-    let state_inner = state.inner().clone();
-
-    thread::spawn(move || {
-        let mut buf = [0u8; 8192];
-        loop {
-            if shutdown_flag.load(Ordering::SeqCst) {
-                break;
-            }
-            match reader.read(&mut buf) {
-                Ok(n) if n > 0 => {
-                    if shutdown_flag.load(Ordering::SeqCst) {
-                        break;
-                    }
-                    let data = String::from_utf8_lossy(&buf[..n]).to_string();
-                    let _ = app_clone.emit(
-                        "hidden-terminal-output",
-                        serde_json::json!({
-                            "session_id": session_id_clone,
-                            "data": data,
-                        }),
-                    );
-                }
-                Ok(_) => {
-                    // Process exited - auto-cleanup
-                    let _ = app_clone.emit(
-                        "hidden-terminal-closed",
-                        serde_json::json!({"session_id": session_id_clone}),
-                    );
-                    // Remove from state
-                    if let Ok(mut st) = state_inner.lock() {
-                        st.pty_sessions.remove(&session_id_clone);
-                    }
-                    break;
-                }
-                Err(_) => {
-                    let _ = app_clone.emit(
-                        "hidden-terminal-closed",
-                        serde_json::json!({"session_id": session_id_clone, "error": true}),
-                    );
-                    if let Ok(mut st) = state_inner.lock() {
-                        st.pty_sessions.remove(&session_id_clone);
-                    }
-                    break;
-                }
+) -> Result<serde_json::Value, String> {
+    // Grab the buffer handle, then release the global lock before copying
+    let scrollback = {
+        let state_lock = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        match state_lock.pty_sessions.get(&session_id) {
+            Some(session) => session.scrollback.clone(),
+            None if state_lock.daemon_sessions.contains_key(&session_id) => {
+                drop(state_lock);
+                return daemon_scrollback(session_id, max_bytes);
             }
+            None => return Err(format!("Session not found: {}", session_id)),
         }
-    });
-
-    let session = crate::state::PtySession {
-        master,
-        child,
-        writer,
-        shutdown,
-        sandboxed: false,
     };
 
-    state
+    let sb = scrollback
         .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?
-        .pty_sessions
-        .insert(session_id.clone(), session);
+        .map_err(|e| format!("Failed to lock scrollback: {}", e))?;
+    let bytes = sb.snapshot(max_bytes);
+
+    Ok(serde_json::json!({
+        "session_id": session_id,
+        "data": String::from_utf8_lossy(&bytes),
+        "total_bytes": sb.total_written(),
+        "truncated": sb.total_written() > bytes.len() as u64,
+    }))
+}
 
-    Ok(session_id)
+fn daemon_scrollback(session_id: String, max_bytes: Option<usize>) -> Result<serde_json::Value, String> {
+    let request = DaemonRequest::Scrollback {
+        session_id: session_id.clone(),
+        max_bytes,
+    };
+    match daemon_client::request(&request)? {
+        crate::session_daemon::types::DaemonResponse::Scrollback { data } => Ok(serde_json::json!({
+            "session_id": session_id,
+            "data": data,
+        })),
+        other => Err(format!("Unexpected daemon response: {:?}", other)),
+    }
 }
 
 #[tauri::command]
@@ -378,11 +553,16 @@ Requirements:
 
 Git diff to analyze:";
 
+/// Start generating a commit message for the staged changes. Returns a job
+/// id; the message arrives as the `result` of `generation-finished`.
 #[tauri::command(async)]
 pub fn generate_commit_message(
     project_dir: String,
     cli: String,
     custom_prompt: Option<String>,
+    timeout_secs: Option<u64>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     // Get staged diff using git diff --cached via shell command
     let diff_output = std::process::Command::new("git")
@@ -427,68 +607,12 @@ pub fn generate_commit_message(
     };
 
     let full_prompt = format!("{}\n\n{}", base_prompt, truncated_diff);
-    let escaped_prompt = full_prompt.replace('\'', "'\\''");
-
-    // Try with free model first
-    let (command, fallback_command) = match cli.as_str() {
-        "opencode" => (
-            format!("opencode run -m opencode/glm-5-free '{}'", escaped_prompt),
-            Some(format!(
-                "opencode run -m opencode/minimax '{}'",
-                escaped_prompt
-            )),
-        ),
-        "claude" | "claude-code" => (
-            format!("claude --model sonnet --print '{}'", escaped_prompt),
-            None,
-        ),
-        _ => (
-            format!("claude --model sonnet --print '{}'", escaped_prompt),
-            None,
-        ),
-    };
 
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-
-    // First attempt with free model
-    let output = std::process::Command::new(&shell)
-        .args(&["-lc", &command])
-        .current_dir(&project_dir)
-        .env("TERM", "xterm-256color")
-        .output()
-        .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-    if output.status.success() {
-        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
-    }
-
-    // If free model failed and we have a fallback (paid model), try it silently
-    if let Some(fallback) = fallback_command {
-        let fallback_output = std::process::Command::new(&shell)
-            .args(&["-lc", &fallback])
-            .current_dir(&project_dir)
-            .env("TERM", "xterm-256color")
-            .output()
-            .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-        if fallback_output.status.success() {
-            return Ok(String::from_utf8_lossy(&fallback_output.stdout)
-                .trim()
-                .to_string());
-        }
-
-        // Both failed - return error from paid model attempt
-        let stdout = String::from_utf8_lossy(&fallback_output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&fallback_output.stderr).to_string();
-        let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-        return Err(format!("LLM command failed: {}", error_msg));
-    }
-
-    // No fallback available, return original error
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-    Err(format!("LLM command failed: {}", error_msg))
+    let runner = LlmRunner::for_task(TASK_COMMIT_MESSAGE, &cli, &project_dir)?
+        .with_timeout(timeout_secs.map(Duration::from_secs));
+    generation::start(app, state.inner(), runner, full_prompt, |response| {
+        Ok(serde_json::Value::String(response.text))
+    })
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -505,13 +629,18 @@ pub struct GenerateTasksResult {
     pub last_commit_hash: String,
 }
 
+/// Start summarising the branch's changes as tasks. Returns a job id; the
+/// `GenerateTasksResult` arrives with `generation-finished`.
 #[tauri::command(async)]
 pub fn generate_branch_tasks(
     project_dir: String,
     base_branch: String,
     current_branch: String,
     cli: String,
-) -> Result<GenerateTasksResult, String> {
+    timeout_secs: Option<u64>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
     eprintln!(
         "[generate_branch_tasks] Starting with cli={}, base_branch={}, current_branch={}",
         cli, base_branch, current_branch
@@ -550,12 +679,13 @@ pub fn generate_branch_tasks(
             _ => String::new(),
         };
 
-        return Ok(GenerateTasksResult {
+        let result = GenerateTasksResult {
             base_branch,
             current_branch,
             tasks: vec![],
             last_commit_hash,
-        });
+        };
+        return generation::completed(&app, TASK_BRANCH_TASKS, serde_json::to_value(result).map_err(|e| e.to_string())?);
     }
 
     // Also get list of changed files
@@ -604,131 +734,44 @@ pub fn generate_branch_tasks(
         TASK_GENERATION_PROMPT, files_section, truncated_diff
     );
 
-    // Try with free model first
-    let (command, fallback_command) = match cli.as_str() {
-        "opencode" => (
-            "opencode run -m opencode/glm-5-free".to_string(),
-            Some("opencode run -m opencode/minimax".to_string()),
-        ),
-        "claude" | "claude-code" => ("claude --model sonnet --print".to_string(), None),
-        _ => ("claude --model sonnet --print".to_string(), None),
-    };
-
-    eprintln!("[generate_branch_tasks] Running command: {}", command);
-
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-
-    // First attempt with free model - pass prompt via stdin to avoid arg length limits
-    let output = std::process::Command::new(&shell)
-        .args(&["-lc", &command])
-        .current_dir(&project_dir)
-        .env("TERM", "xterm-256color")
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()
-        .and_then(|mut child| {
-            use std::io::Write;
-            if let Some(mut stdin) = child.stdin.take() {
-                stdin.write_all(full_prompt.as_bytes())?;
-                stdin.flush()?;
-            }
-            child.wait_with_output()
-        })
-        .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-    eprintln!(
-        "[generate_branch_tasks] Command exit status: {}",
-        output.status
-    );
-
-    let response = if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        eprintln!(
-            "[generate_branch_tasks] Command succeeded, stdout length: {}",
-            stdout.len()
-        );
-        stdout
-    } else if let Some(fallback) = fallback_command {
-        eprintln!("[generate_branch_tasks] Primary command failed, trying fallback");
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        eprintln!("[generate_branch_tasks] Primary stderr: {}", stderr);
-        // Try fallback - also pass prompt via stdin
-        let fallback_output = std::process::Command::new(&shell)
-            .args(&["-lc", &fallback])
-            .current_dir(&project_dir)
-            .env("TERM", "xterm-256color")
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                use std::io::Write;
-                if let Some(mut stdin) = child.stdin.take() {
-                    stdin.write_all(full_prompt.as_bytes())?;
-                    stdin.flush()?;
-                }
-                child.wait_with_output()
-            })
-            .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-        if fallback_output.status.success() {
-            let stdout = String::from_utf8_lossy(&fallback_output.stdout)
-                .trim()
-                .to_string();
-            eprintln!(
-                "[generate_branch_tasks] Fallback succeeded, stdout length: {}",
-                stdout.len()
-            );
-            stdout
-        } else {
-            let stdout = String::from_utf8_lossy(&fallback_output.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&fallback_output.stderr).to_string();
-            eprintln!(
-                "[generate_branch_tasks] Fallback failed. stdout: {}, stderr: {}",
-                stdout, stderr
-            );
-            let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-            return Err(format!("LLM command failed: {}", error_msg));
-        }
-    } else {
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let runner = LlmRunner::for_task(TASK_BRANCH_TASKS, &cli, &project_dir)?
+        .with_timeout(timeout_secs.map(Duration::from_secs));
+    generation::start(app, state.inner(), runner, full_prompt, move |response| {
+        let response = response.text;
         eprintln!(
-            "[generate_branch_tasks] Primary failed and no fallback. stdout: {}, stderr: {}",
-            stdout, stderr
+            "[generate_branch_tasks] Response length: {}",
+            response.len()
         );
-        let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-        return Err(format!("LLM command failed: {}", error_msg));
-    };
 
-    // Parse JSON response
-    let tasks = parse_llm_task_response(&response, &changed_files).map_err(|e| {
-        format!(
-            "Failed to parse LLM response: {}. Raw response preview: {}",
-            e,
-            &response[..response.len().min(200)]
-        )
-    })?;
+        // Parse JSON response
+        let tasks = parse_llm_task_response(&response, &changed_files).map_err(|e| {
+            format!(
+                "Failed to parse LLM response: {}. Raw response preview: {}",
+                e,
+                &response[..response.len().min(200)]
+            )
+        })?;
 
-    // Get the latest commit hash
-    let hash_output = std::process::Command::new("git")
-        .args(&["rev-parse", "HEAD"])
-        .current_dir(&project_dir)
-        .output();
+        // Get the latest commit hash
+        let hash_output = std::process::Command::new("git")
+            .args(&["rev-parse", "HEAD"])
+            .current_dir(&project_dir)
+            .output();
 
-    let last_commit_hash = match hash_output {
-        Ok(output) if output.status.success() => {
-            String::from_utf8_lossy(&output.stdout).trim().to_string()
-        }
-        _ => String::new(),
-    };
+        let last_commit_hash = match hash_output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            }
+            _ => String::new(),
+        };
 
-    Ok(GenerateTasksResult {
-        base_branch,
-        current_branch,
-        tasks,
-        last_commit_hash,
+        serde_json::to_value(GenerateTasksResult {
+            base_branch,
+            current_branch,
+            tasks,
+            last_commit_hash,
+        })
+        .map_err(|e| e.to_string())
     })
 }
 
@@ -844,18 +887,7 @@ pub fn check_pty_child_process(
     process_name: String,
     state: tauri::State<AppState>,
 ) -> Result<bool, String> {
-    let state_lock = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let session = state_lock
-        .pty_sessions
-        .get(&session_id)
-        .ok_or_else(|| format!("Session not found: {}", session_id))?;
-
-    let shell_pid = session
-        .child
-        .process_id()
-        .ok_or_else(|| "Process has no PID".to_string())?;
+    let shell_pid = process_tree::session_root_pid(state.inner(), &session_id)?;
 
     let mut sys = System::new();
     sys.refresh_processes(ProcessesToUpdate::All, true);
@@ -864,7 +896,7 @@ pub fn check_pty_child_process(
     for (pid, process) in sys.processes() {
         let name: String = process.name().to_string_lossy().to_lowercase();
         if name.contains(&target) {
-            if is_descendant(&sys, *pid, Pid::from_u32(shell_pid)) {
+            if process_tree::is_descendant(&sys, *pid, Pid::from_u32(shell_pid)) {
                 return Ok(true);
             }
         }
@@ -872,45 +904,15 @@ pub fn check_pty_child_process(
     Ok(false)
 }
 
-fn is_descendant(sys: &System, pid: Pid, ancestor: Pid) -> bool {
-    let mut current = pid;
-    for _ in 0..10 {
-        if let Some(proc_info) = sys.process(current) {
-            if let Some(parent) = proc_info.parent() {
-                if parent == ancestor {
-                    return true;
-                }
-                current = parent;
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    false
-}
-
-/// Kill a named child process running under the PTY session (e.g. claude, opencode).
-/// Sends SIGKILL to terminate the process immediately.
-#[tauri::command]
+/// Stop a named child process running under the PTY session (e.g. claude, opencode).
+/// Escalates from SIGINT to SIGKILL so the CLI gets a chance to save its session.
+#[tauri::command(async)]
 pub fn kill_pty_child_process(
     session_id: String,
     process_name: String,
-    state: tauri::State<AppState>,
+    state: tauri::State<'_, AppState>,
 ) -> Result<bool, String> {
-    let state_lock = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let session = state_lock
-        .pty_sessions
-        .get(&session_id)
-        .ok_or_else(|| format!("Session not found: {}", session_id))?;
-
-    let shell_pid = session
-        .child
-        .process_id()
-        .ok_or_else(|| "Process has no PID".to_string())?;
+    let shell_pid = process_tree::session_root_pid(state.inner(), &session_id)?;
 
     let mut sys = System::new();
     sys.refresh_processes(ProcessesToUpdate::All, true);
@@ -919,8 +921,8 @@ pub fn kill_pty_child_process(
     for (pid, process) in sys.processes() {
         let name: String = process.name().to_string_lossy().to_lowercase();
         if name.contains(&target) {
-            if is_descendant(&sys, *pid, Pid::from_u32(shell_pid)) {
-                process.kill();
+            if process_tree::is_descendant(&sys, *pid, Pid::from_u32(shell_pid)) {
+                signals::deliver(shell_pid, &pid.to_string(), signals::DEFAULT_ESCALATION, false)?;
                 return Ok(true);
             }
         }
@@ -928,13 +930,17 @@ pub fn kill_pty_child_process(
     Ok(false)
 }
 
-/// Generate an implementation prompt from conversation messages using hidden CLI
+/// Generate an implementation prompt from conversation messages using hidden CLI.
+/// Returns a job id; the prompt arrives with `generation-finished`.
 #[tauri::command(async)]
 pub fn generate_instance_sync_prompt(
     project_dir: String,
     cli: String,
     prompt_type: String,
     messages: Vec<ConversationMessage>,
+    timeout_secs: Option<u64>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     if messages.is_empty() {
         return Err("No conversation messages provided".to_string());
@@ -974,67 +980,9 @@ Follow .orchestration/orchestration.md"#,
         context = context_block
     );
 
-    // Escape single quotes for shell safety
-    let escaped_prompt = full_prompt.replace('\'', "'\\''");
-
-    // Build the CLI command
-    let (command, fallback_command) = match cli.as_str() {
-        "opencode" => (
-            format!("opencode run -m opencode/glm-5-free '{}'", escaped_prompt),
-            Some(format!(
-                "opencode run -m opencode/minimax '{}'",
-                escaped_prompt
-            )),
-        ),
-        "claude" | "claude-code" => (
-            format!("claude --model sonnet --print '{}'", escaped_prompt),
-            None,
-        ),
-        _ => (
-            format!("claude --model sonnet --print '{}'", escaped_prompt),
-            None,
-        ),
-    };
-
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-
-    // First attempt with free model
-    let output = std::process::Command::new(&shell)
-        .args(&["-lc", &command])
-        .current_dir(&project_dir)
-        .env("TERM", "xterm-256color")
-        .output()
-        .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-    if output.status.success() {
-        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
-    }
-
-    // If free model failed and we have a fallback (paid model), try it silently
-    if let Some(fallback) = fallback_command {
-        let fallback_output = std::process::Command::new(&shell)
-            .args(&["-lc", &fallback])
-            .current_dir(&project_dir)
-            .env("TERM", "xterm-256color")
-            .output()
-            .map_err(|e| format!("Failed to run LLM command: {}", e))?;
-
-        if fallback_output.status.success() {
-            return Ok(String::from_utf8_lossy(&fallback_output.stdout)
-                .trim()
-                .to_string());
-        }
-
-        // Both failed - return error from paid model attempt
-        let stdout = String::from_utf8_lossy(&fallback_output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&fallback_output.stderr).to_string();
-        let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-        return Err(format!("LLM command failed: {}", error_msg));
-    }
-
-    // No fallback available, return original error
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let error_msg = if !stdout.is_empty() { stdout } else { stderr };
-    Err(format!("LLM command failed: {}", error_msg))
+    let runner = LlmRunner::for_task(TASK_INSTANCE_SYNC_PROMPT, &cli, &project_dir)?
+        .with_timeout(timeout_secs.map(Duration::from_secs));
+    generation::start(app, state.inner(), runner, full_prompt, |response| {
+        Ok(serde_json::Value::String(response.text))
+    })
 }
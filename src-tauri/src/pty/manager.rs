@@ -1,9 +1,62 @@
 use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
+use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
-use crate::state::PtySession;
+use std::sync::{Arc, Mutex};
+use crate::pty::scrollback::{ScrollbackBuffer, DEFAULT_SCROLLBACK_BYTES};
+use crate::sandbox::{SandboxOptions, SandboxRequest};
+use crate::state::{PtySession, PtyWriter};
 
-pub fn spawn_pty(rows: u16, cols: u16, sandbox: bool, sandbox_no_net: bool, project_dir: Option<String>) -> Result<PtySession, String> {
+/// What to run inside a new terminal. An empty spec means the user's login shell.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SpawnSpec {
+    /// Executable to run; defaults to `$SHELL` (PowerShell on Windows)
+    pub program: Option<String>,
+    pub args: Vec<String>,
+    /// Extra variables set on top of the inherited (or sandbox passthrough) environment
+    pub env: HashMap<String, String>,
+    /// Start as a login shell (`-l`); defaults to true only when `program` is unset
+    pub login: Option<bool>,
+    /// Initial working directory; defaults to the project directory
+    pub cwd: Option<String>,
+    /// Load OSC 7/133 hooks into bash, zsh or fish (interactive shells only)
+    pub shell_integration: Option<bool>,
+}
+
+impl SpawnSpec {
+    /// Program followed by its arguments
+    fn argv(&self) -> Vec<String> {
+        let mut argv = vec![self.program.clone().unwrap_or_else(get_shell)];
+        #[cfg(unix)]
+        if self.login.unwrap_or(self.program.is_none()) {
+            argv.push("-l".to_string());
+        }
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+}
+
+/// Deletes an overlay created for a spawn that then failed. Disarmed once the
+/// session owns the overlay.
+struct OverlayGuard(Option<String>);
+
+impl Drop for OverlayGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.0.take() {
+            if let Err(e) = crate::sandbox::overlay::delete_overlay(id.clone()) {
+                eprintln!("[sandbox] Failed to remove overlay {} after a failed spawn: {}", id, e);
+            }
+        }
+    }
+}
+
+fn command_from_argv(argv: &[String]) -> CommandBuilder {
+    let mut c = CommandBuilder::new(&argv[0]);
+    c.args(&argv[1..]);
+    c
+}
+
+pub fn spawn_pty(rows: u16, cols: u16, sandbox: bool, sandbox_no_net: bool, project_dir: Option<String>, spec: &SpawnSpec, sandbox_options: &SandboxOptions) -> Result<PtySession, String> {
     let pty_system = NativePtySystem::default();
 
     // Create a new PTY with the specified size
@@ -16,116 +69,85 @@ pub fn spawn_pty(rows: u16, cols: u16, sandbox: bool, sandbox_no_net: bool, proj
         })
         .map_err(|e| format!("Failed to open PTY: {}", e))?;
 
-    // Determine the shell to use based on the platform
-    let shell = get_shell();
+    // Shell integration changes how the shell starts, so it goes before any wrapping
+    let integrated;
+    let spec = if spec.shell_integration.unwrap_or(false) {
+        let program = spec.program.clone().unwrap_or_else(get_shell);
+        integrated = crate::pty::shell_integration::inject(spec, program, spec.login.unwrap_or(spec.program.is_none()))?;
+        &integrated
+    } else {
+        spec
+    };
 
-    // Create command: wrap in bwrap sandbox on Unix if requested
-    #[cfg(target_os = "linux")]
-    let mut cmd = if sandbox {
-        let mut c = CommandBuilder::new("/usr/bin/bwrap");
-        // Clear all inherited env vars to prevent leaking secrets
-        c.args(&["--clearenv"]);
-        c.args(&[
-            "--ro-bind", "/", "/",
-            "--dev", "/dev",
-            "--tmpfs", "/tmp",
-        ]);
-        // Home directory: writable by default, with sensitive paths read-only
-        if let Some(ref home) = home_dir() {
-            c.args(&["--bind", home, home]);
+    // Determine what to run: the user's shell unless the spec says otherwise
+    let argv = spec.argv();
 
-            // Sensitive paths that should be read-only inside the sandbox
-            let protected_paths = [
-                ".ssh",
-                ".gnupg",
-                ".bashrc",
-                ".bash_profile",
-                ".profile",
-                ".zshrc",
-                ".zprofile",
-                ".pam_environment",
-                ".gitconfig",
-                ".config/git",
-                ".config/autostart",
-                ".config/systemd/user",
-                ".config/environment.d",
-                ".local/bin",
-                // Shell configs
-                ".config/fish",
-                ".config/nushell",
-                // Desktop environment startup scripts
-                ".config/plasma-workspace/env",
-                ".config/hypr",
-                ".xinitrc",
-                ".xprofile",
-                // File handler and .desktop registration
-                ".config/mimeapps.list",
-                ".local/share/applications",
-            ];
-            for subpath in &protected_paths {
-                let full = format!("{}/{}", home, subpath);
-                if std::path::Path::new(&full).exists() {
-                    c.args(&["--ro-bind", &full, &full]);
-                }
-            }
-        }
-        // Writable: project directory (may be outside home)
-        if let Some(ref proj) = project_dir {
-            if std::path::Path::new(proj).is_dir() {
-                c.args(&["--bind", proj, proj]);
-            }
-        }
-        if sandbox_no_net {
-            c.args(&["--unshare-net"]);
-        }
-        c.args(&[
-            "--unshare-uts",
-            "--unshare-ipc",
-            "--unshare-pid",
-            "--unshare-cgroup",
-            "--new-session",
-            "--die-with-parent",
-            // Mount /proc after --unshare-pid so it's scoped to sandbox PIDs
-            "--proc", "/proc",
-        ]);
-        // Re-export only essential env vars
-        let passthrough_vars = [
-            "HOME", "USER", "LOGNAME", "PATH", "SHELL", "TERM",
-            "LANG", "LC_ALL", "XDG_RUNTIME_DIR", "DBUS_SESSION_BUS_ADDRESS",
-            "DISPLAY", "WAYLAND_DISPLAY",
-            // Claude Code / Node.js needs
-            "NODE_HOME", "NVM_DIR", "npm_config_prefix",
-            // API keys Claude Code needs to function
-            "ANTHROPIC_API_KEY", "CLAUDE_API_KEY",
-        ];
-        for var in &passthrough_vars {
-            if let Ok(val) = std::env::var(var) {
-                c.args(&["--setenv", var, &val]);
-            }
-        }
-        c.args(&["--", &shell, "-l"]);
-        c
+    // Pick the sandbox backend up front: a missing one is an error, never an
+    // unconfined shell
+    let backend = if sandbox {
+        Some(crate::sandbox::select_backend(sandbox_options.backend, sandbox_options.overlay)?)
     } else {
-        let mut c = CommandBuilder::new(&shell);
-        c.arg("-l");
-        c
+        None
     };
 
-    #[cfg(all(unix, not(target_os = "linux")))]
-    let mut cmd = {
-        let mut c = CommandBuilder::new(&shell);
-        c.arg("-l");
-        c
+    // Copy-on-write overlay for the project, created before bwrap mounts it
+    let mut overlay = if backend.is_some() && sandbox_options.overlay {
+        let proj = project_dir
+            .as_ref()
+            .filter(|p| std::path::Path::new(p).is_dir())
+            .ok_or_else(|| "Overlay sandbox requires a project directory".to_string())?;
+        Some(crate::sandbox::overlay::create_overlay(proj)?)
+    } else {
+        None
     };
+    let mut overlay_guard = OverlayGuard(overlay.as_ref().map(|info| info.id.clone()));
 
-    #[cfg(not(unix))]
-    let mut cmd = CommandBuilder::new(&shell);
+    // Resource limits wrap whatever ends up running, the sandbox included
+    let limits = sandbox_options.limits.clone().unwrap_or_default();
+    #[cfg(not(target_os = "linux"))]
+    if !limits.is_empty() {
+        return Err("Resource limits are only supported on Linux".to_string());
+    }
+
+    let mut masked_secrets = Vec::new();
+    let (inner_argv, sandbox_env) = match &backend {
+        Some(backend) => {
+            let mut policy = crate::sandbox::SandboxPolicy::resolve(project_dir.as_deref())?;
+            if sandbox_no_net {
+                policy.network = crate::sandbox::NetworkMode::None;
+            }
+            let overlay_dirs = match &overlay {
+                Some(info) => Some((
+                    crate::sandbox::overlay::upper_dir(&info.id)?.to_string_lossy().to_string(),
+                    crate::sandbox::overlay::work_dir(&info.id)?.to_string_lossy().to_string(),
+                )),
+                None => None,
+            };
+            let home = home_dir();
+            let command = backend.command(&SandboxRequest {
+                policy: &policy,
+                home: home.as_deref(),
+                project_dir: project_dir.as_deref(),
+                overlay: overlay_dirs.as_ref().map(|(u, w)| (u.as_str(), w.as_str())),
+                env: &spec.env,
+                argv: &argv,
+            })?;
+            masked_secrets = policy.masked_secrets;
+            (command.argv, command.env)
+        }
+        None => (argv.clone(), Vec::new()),
+    };
+    let (full_argv, limit_enforcement, limit_scope) = limits.wrap_argv(inner_argv)?;
+    let mut cmd = command_from_argv(&full_argv);
+    for (key, val) in &sandbox_env {
+        cmd.env(key, val);
+    }
 
     // On Windows, PowerShell's Set-Location (cd) doesn't call Win32 SetCurrentDirectory,
     // so the OS-level CWD never updates. Override the prompt function to sync them,
     // allowing sysinfo to read the actual CWD.
     #[cfg(windows)]
-    {
+    if spec.program.is_none() {
         cmd.arg("-NoExit");
         cmd.arg("-Command");
         cmd.arg("function prompt { [System.IO.Directory]::SetCurrentDirectory($PWD.Path); \"PS $($PWD.Path)> \" }");
@@ -133,9 +155,14 @@ pub fn spawn_pty(rows: u16, cols: u16, sandbox: bool, sandbox_no_net: bool, proj
 
     // Set TERM so the shell knows terminal capabilities (line wrapping, cursor movement, etc.)
     cmd.env("TERM", "xterm-256color");
+    for (key, val) in &spec.env {
+        cmd.env(key, val);
+    }
 
-    let start_dir = project_dir
+    let start_dir = spec
+        .cwd
         .as_ref()
+        .or(project_dir.as_ref())
         .filter(|p| std::path::Path::new(p).is_dir())
         .cloned()
         .unwrap_or_else(|| home_dir().unwrap_or_else(|| {
@@ -147,65 +174,118 @@ pub fn spawn_pty(rows: u16, cols: u16, sandbox: bool, sandbox_no_net: bool, proj
     cmd.cwd(&start_dir);
 
     // Spawn the child process
-    eprintln!("[sandbox] sandbox={}, project_dir={:?}", sandbox, project_dir);
-    #[cfg(target_os = "linux")]
-    let (child, actually_sandboxed) = if sandbox {
-        // Ensure bwrap AppArmor profile exists on Ubuntu before first attempt
-        ensure_bwrap_apparmor();
-        match pty_pair.slave.spawn_command(cmd) {
-            Ok(child) => (child, true),
-            Err(e) => {
-                eprintln!("[sandbox] bwrap failed ({}), falling back to unsandboxed", e);
-                let mut fallback = CommandBuilder::new(&shell);
-                fallback.arg("-l");
-                fallback.env("TERM", "xterm-256color");
-                fallback.cwd(&start_dir);
-                let child = pty_pair.slave.spawn_command(fallback)
-                    .map_err(|e| format!("Failed to spawn shell: {}", e))?;
-                (child, false)
-            }
+    let sandbox_backend = backend.as_ref().map(|b| b.kind());
+    eprintln!("[sandbox] backend={:?}, project_dir={:?}", sandbox_backend, project_dir);
+    let mut child = pty_pair.slave.spawn_command(cmd).map_err(|e| match sandbox_backend {
+        Some(kind) => format!("Failed to start {:?} sandbox: {}", kind, e),
+        None => format!("Failed to spawn shell: {}", e),
+    })?;
+    eprintln!("[sandbox] spawned pid={:?}, backend={:?}", child.process_id(), sandbox_backend);
+
+    // Record who mounted the overlay so it isn't modified while in use
+    if let Some(info) = overlay.as_mut() {
+        info.pid = child.process_id();
+        if let Err(e) = crate::sandbox::overlay::write_info(info) {
+            // The guard is about to delete the overlay; don't leave the shell inside it
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
         }
-    } else {
-        let child = pty_pair.slave.spawn_command(cmd)
-            .map_err(|e| format!("Failed to spawn shell: {}", e))?;
-        (child, false)
-    };
-    #[cfg(not(target_os = "linux"))]
-    let (child, actually_sandboxed) = {
-        let child = pty_pair.slave.spawn_command(cmd)
-            .map_err(|e| format!("Failed to spawn shell: {}", e))?;
-        (child, false)
-    };
-    eprintln!("[sandbox] spawned pid={:?}, sandboxed={}", child.process_id(), actually_sandboxed);
+    }
+    let overlay_id = overlay.map(|info| info.id);
 
     // Take writer from master before moving it
     let master = pty_pair.master;
     let writer = master
         .take_writer()
         .map_err(|e| format!("Failed to take writer: {}", e))?;
+    overlay_guard.0 = None;
 
     Ok(PtySession {
         master,
         child,
-        writer,
+        writer: Arc::new(Mutex::new(writer)),
         shutdown: Arc::new(AtomicBool::new(false)),
-        sandboxed: actually_sandboxed,
+        sandboxed: sandbox_backend.is_some(),
+        sandbox_backend,
+        scrollback: Arc::new(Mutex::new(ScrollbackBuffer::new(DEFAULT_SCROLLBACK_BYTES))),
+        recorder: Arc::new(Mutex::new(None)),
+        started_at: std::time::Instant::now(),
+        overlay_id,
+        masked_secrets,
+        limit_enforcement,
+        limit_scope,
+        shell: Arc::new(Mutex::new(crate::pty::shell_integration::ShellTracker::default())),
+        screen: crate::pty::screen::new_screen(rows, cols),
+        last_output: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        output: Arc::new(crate::pty::output::OutputSink::default()),
     })
 }
 
-pub fn write_to_pty(session: &mut PtySession, data: &str) -> Result<(), String> {
+/// How a terminal's child process ended, as reported in `terminal-exited`
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ExitInfo {
+    pub exit_code: Option<u32>,
+    pub signal: Option<String>,
+    pub success: bool,
+}
+
+/// Collect the exit status of a child whose PTY has hit EOF.
+/// The shell is normally already gone by then; if it lingers we give it a
+/// moment and then kill it so it never stays behind as a zombie.
+pub fn reap_child(child: &mut dyn portable_pty::Child) -> ExitInfo {
+    let mut status = None;
+    for _ in 0..20 {
+        match child.try_wait() {
+            Ok(Some(s)) => {
+                status = Some(s);
+                break;
+            }
+            Ok(None) => std::thread::sleep(std::time::Duration::from_millis(50)),
+            Err(_) => break,
+        }
+    }
+    if status.is_none() {
+        let _ = child.kill();
+        status = child.wait().ok();
+    }
+
+    match status {
+        Some(status) => {
+            // portable-pty only exposes the signal name through Display
+            let signal = status
+                .to_string()
+                .strip_prefix("Terminated by ")
+                .map(|s| s.to_string());
+            ExitInfo {
+                exit_code: if signal.is_none() { Some(status.exit_code()) } else { None },
+                signal,
+                success: status.success(),
+            }
+        }
+        None => ExitInfo {
+            exit_code: None,
+            signal: None,
+            success: false,
+        },
+    }
+}
+
+pub fn write_to_pty(writer: &PtyWriter, data: &str) -> Result<(), String> {
     use std::io::Write;
-    session
-        .writer
+    let mut writer = writer
+        .lock()
+        .map_err(|e| format!("Failed to lock PTY writer: {}", e))?;
+    writer
         .write_all(data.as_bytes())
         .map_err(|e| format!("Failed to write to PTY: {}", e))?;
-    session
-        .writer
+    writer
         .flush()
         .map_err(|e| format!("Failed to flush PTY: {}", e))
 }
 
 pub fn resize_pty(session: &mut PtySession, rows: u16, cols: u16) -> Result<(), String> {
+    crate::pty::screen::resize(&session.screen, rows, cols);
     session
         .master
         .resize(PtySize {
@@ -217,65 +297,6 @@ pub fn resize_pty(session: &mut PtySession, rows: u16, cols: u16) -> Result<(),
         .map_err(|e| format!("Failed to resize PTY: {}", e))
 }
 
-/// On Ubuntu 24.04+, AppArmor restricts unprivileged user namespaces.
-/// bwrap needs a profile that grants `userns,` permission (same as Flatpak).
-/// This checks once per process whether the profile exists and installs it via pkexec if needed.
-#[cfg(target_os = "linux")]
-fn ensure_bwrap_apparmor() {
-    use std::sync::Once;
-    static ONCE: Once = Once::new();
-    ONCE.call_once(|| {
-        let profile_path = "/etc/apparmor.d/bwrap";
-        let sysctl_path = "/proc/sys/kernel/apparmor_restrict_unprivileged_userns";
-
-        // Only relevant if AppArmor restricts unprivileged userns
-        let restricted = std::fs::read_to_string(sysctl_path)
-            .map(|v| v.trim() == "1")
-            .unwrap_or(false);
-        if !restricted {
-            eprintln!("[sandbox] AppArmor userns restriction not active, skipping profile setup");
-            return;
-        }
-
-        // Check if profile already exists
-        if std::path::Path::new(profile_path).exists() {
-            eprintln!("[sandbox] bwrap AppArmor profile already installed");
-            return;
-        }
-
-        eprintln!("[sandbox] AppArmor restricts userns; installing bwrap profile via pkexec...");
-
-        let profile_content = r#"abi <abi/4.0>,
-include <tunables/global>
-
-profile bwrap /usr/bin/bwrap flags=(unconfined) {
-  userns,
-}
-"#;
-
-        // Write profile and reload via pkexec (GUI sudo prompt)
-        let script = format!(
-            "echo '{}' > {} && apparmor_parser -r {}",
-            profile_content, profile_path, profile_path
-        );
-        let status = std::process::Command::new("pkexec")
-            .args(["bash", "-c", &script])
-            .status();
-
-        match status {
-            Ok(s) if s.success() => {
-                eprintln!("[sandbox] bwrap AppArmor profile installed successfully");
-            }
-            Ok(s) => {
-                eprintln!("[sandbox] pkexec exited with {}, sandbox may not work", s);
-            }
-            Err(e) => {
-                eprintln!("[sandbox] failed to run pkexec: {}, sandbox may not work", e);
-            }
-        }
-    });
-}
-
 fn home_dir() -> Option<String> {
     #[cfg(unix)]
     { std::env::var("HOME").ok() }
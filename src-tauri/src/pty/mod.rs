@@ -4,9 +4,31 @@
 //!
 //! - `manager`: Core PTY operations (spawn, write, resize)
 //! - `commands`: Tauri commands exposed to the frontend
+//! - `output`: UTF-8 decoding, frame batching and channel delivery of output
+//! - `scrollback`: Bounded output history used to redraw reattached terminals
+//! - `recording`: asciicast v2 recording and replay of sessions
+//! - `process_tree`: sysinfo-based view of everything running under a terminal
+//! - `signals`: targeted signal delivery with escalation policies
+//! - `screen`: server-side VT100 screen model of each terminal
+//! - `agent_state`: busy/idle detection for Claude Code and opencode sessions
+//! - `broadcast`: the same input sent to several terminals or a group
+//! - `paste`: chunked bracketed-paste delivery of large inputs
+//! - `prompt_queue`: prompts held back until the terminal's agent is ready
+//! - `shell_integration`: OSC 7/133 parsing, shell hooks and command history
 //!
 //! Each terminal session is identified by a unique UUID and manages its own
 //! shell process with bidirectional I/O through the PTY.
 
 pub mod manager;
 pub mod commands;
+pub mod scrollback;
+pub mod recording;
+pub mod process_tree;
+pub mod signals;
+pub mod shell_integration;
+pub mod screen;
+pub mod agent_state;
+pub mod prompt_queue;
+pub mod paste;
+pub mod broadcast;
+pub mod output;
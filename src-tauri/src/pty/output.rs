@@ -0,0 +1,275 @@
+use crate::state::AppState;
+use serde::Serialize;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter};
+
+/// Output is coalesced for about one frame before it goes to the webview
+const FRAME_INTERVAL: Duration = Duration::from_millis(12);
+
+/// A batch is sent early once it reaches this size
+const MAX_FRAME_BYTES: usize = 256 * 1024;
+
+/// Reads buffered between the PTY reader and the sender; when full the
+/// reader blocks, and with it the program writing to the terminal
+const QUEUED_READS: usize = 64;
+
+/// With flow control, sending pauses above `HIGH_WATER` unacknowledged bytes
+/// and resumes once the webview has caught up to `LOW_WATER`
+const HIGH_WATER: usize = 2 * 1024 * 1024;
+const LOW_WATER: usize = 512 * 1024;
+
+/// A webview that stops acknowledging (reload, crash) must not freeze the
+/// terminal; after this long flow control is dropped for that channel
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Frames a subscriber may fall behind by before it is dropped
+const SUBSCRIBER_BACKLOG: usize = 256;
+
+/// Decodes a byte stream to UTF-8, carrying sequences split across reads
+/// over to the next call instead of replacing them
+#[derive(Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut out = String::with_capacity(input.len());
+        let mut rest = &input[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    out.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    out.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // Truncated sequence at the end: wait for the rest
+                        None => {
+                            self.pending = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Whatever is left when the stream ends can never complete
+    pub fn finish(&mut self) -> String {
+        if self.pending.is_empty() {
+            String::new()
+        } else {
+            self.pending.clear();
+            char::REPLACEMENT_CHARACTER.to_string()
+        }
+    }
+}
+
+/// One batch of terminal output sent over a session's channel
+#[derive(Serialize, Clone, Debug)]
+pub struct OutputFrame {
+    /// Increases by one per frame, so the frontend can spot gaps
+    pub seq: u64,
+    /// UTF-8 length of `data`, the unit `ack_terminal_output` counts in
+    pub bytes: usize,
+    pub data: String,
+}
+
+#[derive(Default)]
+struct SinkState {
+    channel: Option<Channel<OutputFrame>>,
+    /// Whether the frontend acknowledges frames with `ack_terminal_output`
+    flow_control: bool,
+    unacked: usize,
+    seq: u64,
+    /// Extra read-only consumers of the output, such as web mirror viewers
+    subscribers: Vec<SyncSender<String>>,
+}
+
+/// Where a session's output goes: its channel if the frontend attached one,
+/// the global `terminal-output` event otherwise
+#[derive(Default)]
+pub struct OutputSink {
+    state: Mutex<SinkState>,
+    acked: Condvar,
+}
+
+impl OutputSink {
+    pub fn attach(&self, channel: Channel<OutputFrame>, flow_control: bool) {
+        if let Ok(mut st) = self.state.lock() {
+            st.channel = Some(channel);
+            st.flow_control = flow_control;
+            st.unacked = 0;
+        }
+        self.acked.notify_all();
+    }
+
+    pub fn ack(&self, bytes: usize) {
+        if let Ok(mut st) = self.state.lock() {
+            st.unacked = st.unacked.saturating_sub(bytes);
+            if st.unacked <= LOW_WATER {
+                self.acked.notify_all();
+            }
+        }
+    }
+
+    /// Receive a copy of every frame from now on. The receiver disconnects when
+    /// the session ends, or early if it stops keeping up.
+    pub fn subscribe(&self) -> Receiver<String> {
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_BACKLOG);
+        if let Ok(mut st) = self.state.lock() {
+            st.subscribers.push(tx);
+        }
+        rx
+    }
+
+    /// Deliver a frame, first waiting for the webview if it is too far behind
+    pub fn send(&self, app: &AppHandle, session_id: &str, data: String) {
+        let Ok(mut st) = self.state.lock() else { return };
+        if st.flow_control && st.channel.is_some() && st.unacked > HIGH_WATER {
+            let (guard, result) = match self.acked.wait_timeout_while(st, ACK_TIMEOUT, |s| {
+                s.flow_control && s.channel.is_some() && s.unacked > LOW_WATER
+            }) {
+                Ok(waited) => waited,
+                Err(_) => return,
+            };
+            st = guard;
+            if result.timed_out() {
+                eprintln!("[pty] {} stopped acknowledging output; disabling flow control", session_id);
+                st.flow_control = false;
+            }
+        }
+
+        st.subscribers.retain(|tx| match tx.try_send(data.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                eprintln!("[pty] Dropping a subscriber of {} that fell behind", session_id);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+
+        st.seq += 1;
+        let len = data.len();
+        let frame = OutputFrame { seq: st.seq, bytes: len, data };
+        match &st.channel {
+            Some(channel) => match channel.send(frame) {
+                Ok(()) => {
+                    if st.flow_control {
+                        st.unacked += len;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[pty] Output channel for {} closed: {}", session_id, e);
+                    st.channel = None;
+                }
+            },
+            None => {
+                let _ = app.emit(
+                    "terminal-output",
+                    serde_json::json!({ "session_id": session_id, "data": frame.data }),
+                );
+            }
+        }
+    }
+}
+
+/// Start the thread that decodes a session's raw output, hands the text to
+/// `on_text` (triggers, recording) and sends it on in frame-sized batches.
+/// Dropping the returned sender flushes what is left and ends the thread.
+pub fn spawn_pipeline(
+    app: AppHandle,
+    session_id: String,
+    sink: Arc<OutputSink>,
+    mut on_text: impl FnMut(&str) + Send + 'static,
+) -> (SyncSender<Vec<u8>>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(QUEUED_READS);
+    let handle = std::thread::spawn(move || {
+        let mut decoder = Utf8Decoder::default();
+        let mut batch = String::new();
+        let mut batch_started: Option<Instant> = None;
+
+        loop {
+            let received = match batch_started {
+                Some(started) => rx.recv_timeout(FRAME_INTERVAL.saturating_sub(started.elapsed())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let done = match received {
+                Ok(bytes) => {
+                    let text = decoder.decode(&bytes);
+                    if !text.is_empty() {
+                        on_text(&text);
+                        batch.push_str(&text);
+                        batch_started.get_or_insert_with(Instant::now);
+                    }
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => {
+                    batch.push_str(&decoder.finish());
+                    true
+                }
+            };
+
+            let frame_due = batch_started.is_some_and(|t| t.elapsed() >= FRAME_INTERVAL);
+            if !batch.is_empty() && (done || frame_due || batch.len() >= MAX_FRAME_BYTES) {
+                sink.send(&app, &session_id, std::mem::take(&mut batch));
+                batch_started = None;
+            }
+            if done {
+                break;
+            }
+        }
+    });
+    (tx, handle)
+}
+
+fn session_sink(state: &AppState, session_id: &str) -> Result<Arc<OutputSink>, String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if let Some(session) = st.pty_sessions.get(session_id) {
+        Ok(session.output.clone())
+    } else if let Some(attachment) = st.daemon_sessions.get(session_id) {
+        Ok(attachment.output.clone())
+    } else {
+        Err(format!("Session not found: {}", session_id))
+    }
+}
+
+/// Stream a terminal's output over `on_output` instead of the global
+/// `terminal-output` event, e.g. after the terminal view was remounted.
+/// With `flow_control` (the default) the frontend must report consumed bytes
+/// through `ack_terminal_output`.
+#[tauri::command]
+pub fn attach_terminal_output(
+    session_id: String,
+    on_output: Channel<OutputFrame>,
+    flow_control: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<(), String> {
+    session_sink(state.inner(), &session_id)?.attach(on_output, flow_control.unwrap_or(true));
+    Ok(())
+}
+
+/// Report how many bytes of output the webview has finished rendering
+#[tauri::command]
+pub fn ack_terminal_output(session_id: String, bytes: usize, state: tauri::State<AppState>) -> Result<(), String> {
+    session_sink(state.inner(), &session_id)?.ack(bytes);
+    Ok(())
+}
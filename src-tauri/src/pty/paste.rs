@@ -0,0 +1,259 @@
+use crate::pty::screen::ScreenModel;
+use crate::session_daemon::{client as daemon_client, types::DaemonRequest};
+use crate::state::{AppState, PtyWriter};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// Bytes per write to a local PTY. Small enough that a TUI reading stdin
+/// keeps up; the blocking write is the backpressure.
+const CHUNK_BYTES: usize = 4096;
+
+/// Bytes per write request to the session daemon
+const DAEMON_CHUNK_BYTES: usize = 32 * 1024;
+
+/// Breather between chunks for TUIs that parse input as it arrives
+const CHUNK_PAUSE: Duration = Duration::from_millis(2);
+
+/// Give the agent a moment to take in the paste before Enter submits it
+const SUBMIT_DELAY: Duration = Duration::from_millis(150);
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Spilled pastes older than this are removed on the next spill
+const SPILL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PasteOptions {
+    /// Wrap in bracketed-paste markers; by default only when the program
+    /// running in the terminal has turned bracketed paste on
+    pub bracketed: Option<bool>,
+    /// Press Enter after the paste
+    pub submit: bool,
+    /// Above this many bytes, write the text to a file and paste `@<path>`
+    /// (ignored for ssh sessions, which get the text itself)
+    pub spill_over_bytes: Option<usize>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PasteResult {
+    pub paste_id: String,
+    /// Bytes written to the terminal, markers included
+    pub bytes_written: usize,
+    pub bracketed: bool,
+    /// File the text was written to instead, if it was spilled
+    pub spilled_to: Option<String>,
+}
+
+/// A paste that failed, and how much of it reached the terminal first
+#[derive(Debug)]
+pub struct PasteError {
+    pub message: String,
+    pub bytes_written: usize,
+}
+
+impl From<String> for PasteError {
+    /// A failure before anything was written
+    fn from(message: String) -> Self {
+        PasteError { message, bytes_written: 0 }
+    }
+}
+
+enum Target {
+    Local(PtyWriter),
+    Daemon,
+}
+
+/// Where to write, the screen to check for bracketed paste, and whether the
+/// terminal is an ssh session (whose programs can't see our files)
+fn target(state: &AppState, session_id: &str) -> Result<(Target, ScreenModel, bool), String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if let Some(session) = st.pty_sessions.get(session_id) {
+        let remote = st.remote_sessions.contains_key(session_id);
+        Ok((Target::Local(session.writer.clone()), session.screen.clone(), remote))
+    } else if let Some(attachment) = st.daemon_sessions.get(session_id) {
+        Ok((Target::Daemon, attachment.screen.clone(), false))
+    } else {
+        Err(format!("Session not found: {}", session_id))
+    }
+}
+
+fn spill_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("pastes")
+}
+
+/// Write `text` to a file the agent can read, returning its path
+fn spill(paste_id: &str, text: &str) -> Result<String, String> {
+    let dir = spill_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| t.elapsed().unwrap_or_default() > SPILL_MAX_AGE);
+            if stale {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+    let path = dir.join(format!("{}.md", paste_id));
+    std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Split at char boundaries so every piece is valid UTF-8
+fn chunks(data: &str, size: usize) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (head, tail) = rest.split_at(end);
+        out.push(head);
+        rest = tail;
+    }
+    out
+}
+
+/// Paste text into a terminal in chunks, without holding the state lock.
+/// A local terminal's writer stays locked for the whole paste so typed keys
+/// can't land in the middle of it. Emits `paste-progress` along the way.
+pub fn deliver(
+    app: &AppHandle,
+    state: &AppState,
+    session_id: &str,
+    text: &str,
+    options: &PasteOptions,
+) -> Result<PasteResult, PasteError> {
+    let (target, screen, remote) = target(state, session_id)?;
+    let paste_id = uuid::Uuid::new_v4().to_string();
+
+    let spilled_to = match options.spill_over_bytes {
+        // A local path means nothing on the remote host; paste the text itself
+        Some(limit) if text.len() > limit && remote => {
+            eprintln!("[paste] Not spilling {} bytes to a file for ssh session {}", text.len(), session_id);
+            None
+        }
+        Some(limit) if text.len() > limit => Some(spill(&paste_id, text)?),
+        _ => None,
+    };
+    let body = match &spilled_to {
+        Some(path) => format!("@{} ", path),
+        None => text.to_string(),
+    };
+
+    let bracketed = options
+        .bracketed
+        .unwrap_or_else(|| screen.lock().map(|p| p.screen().bracketed_paste()).unwrap_or(false));
+    let data = if bracketed {
+        // Text containing the end marker would end the paste early
+        format!("{}{}{}", PASTE_START, body.replace(PASTE_END, ""), PASTE_END)
+    } else {
+        body
+    };
+
+    let total = data.len();
+    let mut written = 0;
+    let mut last_progress = Instant::now();
+    let mut progress = |written: usize| {
+        if written == total || last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = app.emit(
+                "paste-progress",
+                serde_json::json!({
+                    "session_id": session_id,
+                    "paste_id": paste_id,
+                    "written": written,
+                    "total": total,
+                }),
+            );
+        }
+    };
+
+    match target {
+        Target::Local(writer) => {
+            let mut writer = writer
+                .lock()
+                .map_err(|e| format!("Failed to lock PTY writer: {}", e))?;
+            for chunk in chunks(&data, CHUNK_BYTES) {
+                writer
+                    .write_all(chunk.as_bytes())
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| PasteError {
+                        message: format!("Failed to write to PTY after {} of {} bytes: {}", written, total, e),
+                        bytes_written: written,
+                    })?;
+                written += chunk.len();
+                progress(written);
+                std::thread::sleep(CHUNK_PAUSE);
+            }
+            if options.submit {
+                std::thread::sleep(SUBMIT_DELAY);
+                writer
+                    .write_all(b"\r")
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| PasteError {
+                        message: format!("Failed to write to PTY: {}", e),
+                        bytes_written: written,
+                    })?;
+            }
+        }
+        Target::Daemon => {
+            for chunk in chunks(&data, DAEMON_CHUNK_BYTES) {
+                daemon_client::send(&DaemonRequest::Write {
+                    session_id: session_id.to_string(),
+                    data: chunk.to_string(),
+                })
+                .map_err(|e| PasteError {
+                    message: format!("Failed after {} of {} bytes: {}", written, total, e),
+                    bytes_written: written,
+                })?;
+                written += chunk.len();
+                progress(written);
+            }
+            if options.submit {
+                std::thread::sleep(SUBMIT_DELAY);
+                daemon_client::send(&DaemonRequest::Write {
+                    session_id: session_id.to_string(),
+                    data: "\r".to_string(),
+                })
+                .map_err(|message| PasteError { message, bytes_written: written })?;
+            }
+        }
+    }
+
+    Ok(PasteResult {
+        paste_id,
+        bytes_written: written,
+        bracketed,
+        spilled_to,
+    })
+}
+
+/// Paste (possibly very large) text into a terminal. Runs off the state lock
+/// and reports `paste-progress` events; see `PasteOptions`.
+#[tauri::command(async)]
+pub fn paste_to_terminal(
+    session_id: String,
+    text: String,
+    options: Option<PasteOptions>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<PasteResult, String> {
+    deliver(&app, state.inner(), &session_id, &text, &options.unwrap_or_default()).map_err(|e| e.message)
+}
@@ -0,0 +1,139 @@
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// One process under a terminal, with its own descendants
+#[derive(Serialize, Clone, Debug)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub name: String,
+    pub cmdline: Vec<String>,
+    pub cwd: Option<String>,
+    pub cpu_percent: f32,
+    /// Resident set size
+    pub memory_bytes: u64,
+    /// Unix timestamp (seconds)
+    pub start_time: u64,
+    pub children: Vec<ProcessNode>,
+}
+
+/// PID of the process directly under a terminal's PTY (the shell, or the
+/// sandbox wrapper around it), for local and persistent sessions alike
+pub fn session_root_pid(state: &AppState, session_id: &str) -> Result<u32, String> {
+    let state_lock = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let pid = if let Some(session) = state_lock.pty_sessions.get(session_id) {
+        session.child.process_id()
+    } else if let Some(attachment) = state_lock.daemon_sessions.get(session_id) {
+        attachment.pid
+    } else {
+        return Err(format!("Session not found: {}", session_id));
+    };
+    pid.ok_or_else(|| "Process has no PID".to_string())
+}
+
+/// Snapshot of all processes. With `measure_cpu` it samples twice so CPU
+/// usage is meaningful, which takes sysinfo's minimum update interval.
+pub fn process_snapshot(measure_cpu: bool) -> System {
+    let refresh = ProcessRefreshKind::nothing()
+        .with_memory()
+        .with_cpu()
+        .with_cmd(UpdateKind::OnlyIfNotSet)
+        .with_cwd(UpdateKind::Always);
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+    if measure_cpu {
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+    }
+    sys
+}
+
+/// Whether `pid` sits anywhere below `ancestor`. Guards against parent
+/// cycles, which a recycled pid can briefly produce.
+pub fn is_descendant(sys: &System, pid: Pid, ancestor: Pid) -> bool {
+    let mut seen = HashSet::new();
+    let mut current = pid;
+    while seen.insert(current) {
+        match sys.process(current).and_then(|p| p.parent()) {
+            Some(parent) if parent == ancestor => return true,
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    false
+}
+
+/// Every process below `root`, excluding `root` itself. Threads are skipped.
+pub fn descendants(sys: &System, root: Pid) -> Vec<Pid> {
+    let children = children_map(sys);
+    let mut out = Vec::new();
+    let mut stack = vec![root];
+    let mut seen = HashSet::from([root]);
+    while let Some(pid) = stack.pop() {
+        for child in children.get(&pid).into_iter().flatten() {
+            if seen.insert(*child) {
+                out.push(*child);
+                stack.push(*child);
+            }
+        }
+    }
+    out
+}
+
+fn children_map(sys: &System) -> HashMap<Pid, Vec<Pid>> {
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (pid, process) in sys.processes() {
+        if process.thread_kind().is_some() {
+            continue;
+        }
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*pid);
+        }
+    }
+    for list in children.values_mut() {
+        list.sort();
+    }
+    children
+}
+
+fn node(process: &Process) -> ProcessNode {
+    ProcessNode {
+        pid: process.pid().as_u32(),
+        name: process.name().to_string_lossy().to_string(),
+        cmdline: process.cmd().iter().map(|a| a.to_string_lossy().to_string()).collect(),
+        cwd: process.cwd().map(|p| p.to_string_lossy().to_string()),
+        cpu_percent: process.cpu_usage(),
+        memory_bytes: process.memory(),
+        start_time: process.start_time(),
+        children: Vec::new(),
+    }
+}
+
+fn build_tree(sys: &System, children: &HashMap<Pid, Vec<Pid>>, pid: Pid, seen: &mut HashSet<Pid>) -> Option<ProcessNode> {
+    if !seen.insert(pid) {
+        return None;
+    }
+    let mut tree = node(sys.process(pid)?);
+    for child in children.get(&pid).into_iter().flatten() {
+        if let Some(child_node) = build_tree(sys, children, *child, seen) {
+            tree.children.push(child_node);
+        }
+    }
+    Some(tree)
+}
+
+/// Full process tree under a terminal, rooted at the PTY's direct child
+#[tauri::command(async)]
+pub fn get_terminal_process_tree(
+    session_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ProcessNode, String> {
+    let root = Pid::from_u32(session_root_pid(state.inner(), &session_id)?);
+    let sys = process_snapshot(true);
+    let children = children_map(&sys);
+    build_tree(&sys, &children, root, &mut HashSet::new())
+        .ok_or_else(|| format!("Process {} is no longer running", root))
+}
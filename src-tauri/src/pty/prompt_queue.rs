@@ -0,0 +1,267 @@
+use crate::pty::paste::{self, PasteOptions};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+/// A prompt waiting for its terminal's agent to be ready for input
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueuedPrompt {
+    pub id: String,
+    pub session_id: String,
+    pub text: String,
+    /// Unix timestamp (milliseconds)
+    pub created_at: u64,
+}
+
+/// Pending prompts per terminal, in delivery order
+pub type PromptQueues = HashMap<String, Vec<QueuedPrompt>>;
+
+/// Queued prompts get typed into unconfined terminals, so the file lives in
+/// the app state dir that sandboxes can't see
+fn queue_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("prompt-queue.json")
+}
+
+/// Queues saved by the last run. Only persistent terminals outlive the app,
+/// so queues of any other session are dropped.
+pub fn load_queues() -> PromptQueues {
+    let path = queue_path();
+    // A symlink could have been planted by a sandbox from before the state
+    // dir was hidden, pointing at a file it can still write
+    if path.symlink_metadata().is_ok_and(|m| !m.is_file()) {
+        eprintln!("[prompt-queue] Ignoring {}: not a regular file", path.display());
+        let _ = std::fs::remove_file(&path);
+        return PromptQueues::new();
+    }
+    let mut queues: PromptQueues = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if queues.is_empty() {
+        return queues;
+    }
+    match crate::session_daemon::list_persistent_sessions() {
+        Ok(sessions) => {
+            let before = queues.len();
+            queues.retain(|id, _| sessions.iter().any(|s| &s.session_id == id));
+            if queues.len() != before {
+                eprintln!("[prompt-queue] Dropped {} queue(s) of terminals that are gone", before - queues.len());
+                persist(&queues);
+            }
+        }
+        // Keep them rather than lose prompts of sessions that may still exist
+        Err(e) => eprintln!("[prompt-queue] Could not list persistent sessions: {}", e),
+    }
+    queues
+}
+
+/// Written to a temp file and renamed, which replaces rather than follows
+/// whatever is at the path
+fn persist(queues: &PromptQueues) {
+    let path = queue_path();
+    let tmp = path.with_extension("json.tmp");
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            let _ = std::fs::remove_file(&tmp);
+            std::fs::write(&tmp, serde_json::to_string_pretty(queues).unwrap_or_default())
+        })
+        .and_then(|_| std::fs::rename(&tmp, &path));
+    if let Err(e) = result {
+        eprintln!("[prompt-queue] Failed to write {}: {}", path.display(), e);
+    }
+}
+
+fn emit_changed(app: &AppHandle, session_id: &str, queue: &[QueuedPrompt]) {
+    let _ = app.emit(
+        "prompt-queue-changed",
+        serde_json::json!({ "session_id": session_id, "queue": queue }),
+    );
+}
+
+/// Forget the queue of a terminal that has been closed
+pub fn remove_session(queues: &mut PromptQueues, session_id: &str) {
+    if queues.remove(session_id).is_some() {
+        persist(queues);
+    }
+}
+
+/// Paste the next queued prompt into the terminal and submit it. Returns
+/// false if the queue was empty or the write failed (the prompt stays queued).
+pub fn deliver_next(app: &AppHandle, state: &AppState, session_id: &str) -> bool {
+    let prompt = {
+        let Ok(mut st) = state.lock() else { return false };
+        let Some(queue) = st.prompt_queues.get_mut(session_id).filter(|q| !q.is_empty()) else {
+            return false;
+        };
+        let prompt = queue.remove(0);
+        persist(&st.prompt_queues);
+        prompt
+    };
+
+    let options = PasteOptions {
+        bracketed: Some(true),
+        submit: true,
+        spill_over_bytes: None,
+    };
+    let delivered = paste::deliver(app, state, session_id, &prompt.text, &options);
+
+    let Ok(mut st) = state.lock() else { return false };
+    // The terminal may have been closed while the paste was under way
+    let Some(queue) = st.prompt_queues.get_mut(session_id) else {
+        return delivered.is_ok();
+    };
+    let result = match delivered {
+        Ok(_) => Ok(()),
+        // Nothing reached the terminal: keep the prompt for the next attempt
+        Err(e) if e.bytes_written == 0 => {
+            eprintln!("[prompt-queue] Failed to deliver {} to {}: {}", prompt.id, session_id, e.message);
+            queue.insert(0, prompt.clone());
+            persist(&st.prompt_queues);
+            return false;
+        }
+        // Part of it did; sending it again would repeat that part
+        Err(e) => Err(e.message),
+    };
+    let queue = queue.clone();
+    if queue.is_empty() {
+        st.prompt_queues.remove(session_id);
+        persist(&st.prompt_queues);
+    }
+    drop(st);
+
+    let ok = result.is_ok();
+    match result {
+        Ok(()) => {
+            eprintln!("[prompt-queue] Delivered {} to {}", prompt.id, session_id);
+            let _ = app.emit("prompt-delivered", &prompt);
+        }
+        Err(message) => {
+            eprintln!("[prompt-queue] {} was only partly delivered to {}, dropping it: {}", prompt.id, session_id, message);
+            let _ = app.emit(
+                "prompt-delivery-failed",
+                serde_json::json!({ "prompt": prompt, "error": message }),
+            );
+        }
+    }
+    emit_changed(app, session_id, &queue);
+    ok
+}
+
+/// Queue a prompt for a terminal's agent. It is pasted and submitted once the
+/// agent is back at its input prompt and the terminal has gone quiet.
+#[tauri::command]
+pub fn enqueue_prompt(
+    session_id: String,
+    text: String,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<QueuedPrompt, String> {
+    if text.trim().is_empty() {
+        return Err("Prompt is empty".to_string());
+    }
+    let mut st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if !st.pty_sessions.contains_key(&session_id) && !st.daemon_sessions.contains_key(&session_id) {
+        return Err(format!("Session not found: {}", session_id));
+    }
+    let prompt = QueuedPrompt {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: session_id.clone(),
+        text,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    };
+    let queue = st.prompt_queues.entry(session_id.clone()).or_default();
+    queue.push(prompt.clone());
+    let queue = queue.clone();
+    persist(&st.prompt_queues);
+    drop(st);
+
+    emit_changed(&app, &session_id, &queue);
+    Ok(prompt)
+}
+
+/// Prompts still waiting for a terminal, next first
+#[tauri::command]
+pub fn list_prompt_queue(session_id: String, state: tauri::State<AppState>) -> Result<Vec<QueuedPrompt>, String> {
+    let st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    Ok(st.prompt_queues.get(&session_id).cloned().unwrap_or_default())
+}
+
+/// Put a terminal's queue in the given order. `prompt_ids` must list every
+/// queued prompt exactly once.
+#[tauri::command]
+pub fn reorder_prompt_queue(
+    session_id: String,
+    prompt_ids: Vec<String>,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Vec<QueuedPrompt>, String> {
+    let mut st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let queue = st.prompt_queues.get_mut(&session_id).ok_or("Queue is empty")?;
+
+    let mut reordered: Vec<QueuedPrompt> = Vec::with_capacity(queue.len());
+    for id in &prompt_ids {
+        let prompt = queue
+            .iter()
+            .find(|p| &p.id == id)
+            .ok_or_else(|| format!("Prompt not queued: {}", id))?;
+        if reordered.iter().any(|p| &p.id == id) {
+            return Err(format!("Prompt listed twice: {}", id));
+        }
+        reordered.push(prompt.clone());
+    }
+    // A prompt was delivered or added since the caller listed the queue
+    if reordered.len() != queue.len() {
+        return Err("Queue changed; list it again and retry".to_string());
+    }
+    *queue = reordered;
+    let queue = queue.clone();
+    persist(&st.prompt_queues);
+    drop(st);
+
+    emit_changed(&app, &session_id, &queue);
+    Ok(queue)
+}
+
+/// Drop a queued prompt. Returns false if it was already delivered.
+#[tauri::command]
+pub fn cancel_queued_prompt(
+    session_id: String,
+    prompt_id: String,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<bool, String> {
+    let mut st = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let Some(queue) = st.prompt_queues.get_mut(&session_id) else { return Ok(false) };
+    let before = queue.len();
+    queue.retain(|p| p.id != prompt_id);
+    if queue.len() == before {
+        return Ok(false);
+    }
+    let queue = queue.clone();
+    if queue.is_empty() {
+        st.prompt_queues.remove(&session_id);
+    }
+    persist(&st.prompt_queues);
+    drop(st);
+
+    emit_changed(&app, &session_id, &queue);
+    Ok(true)
+}
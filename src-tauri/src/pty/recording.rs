@@ -0,0 +1,263 @@
+use crate::state::AppState;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Longest pause honoured during replay unless the caller overrides it
+const DEFAULT_REPLAY_MAX_IDLE_SECS: f64 = 2.0;
+
+/// Writes a terminal session to an asciicast v2 file
+/// (https://docs.asciinema.org/manual/asciicast/v2/).
+pub struct CastRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    path: PathBuf,
+}
+
+impl CastRecorder {
+    pub fn create(path: PathBuf, cols: u16, rows: u16) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create recording {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+
+        let header = serde_json::json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            "env": {
+                "SHELL": std::env::var("SHELL").unwrap_or_default(),
+                "TERM": "xterm-256color",
+            },
+        });
+        writeln!(writer, "{}", header).map_err(|e| format!("Failed to write recording: {}", e))?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            path,
+        })
+    }
+
+    pub fn output(&mut self, data: &str) {
+        self.event("o", data);
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.event("r", &format!("{}x{}", cols, rows));
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush recording: {}", e))?;
+        Ok(self.path)
+    }
+
+    fn event(&mut self, kind: &str, data: &str) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if let Ok(line) = serde_json::to_string(&(elapsed, kind, data)) {
+            let _ = writeln!(self.writer, "{}", line);
+        }
+    }
+}
+
+fn recordings_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("recordings")
+}
+
+/// Start teeing a terminal's output into a `.cast` file. Without an explicit
+/// path the recording goes to `~/.lirah/recordings/`. Returns the file path.
+#[tauri::command]
+pub fn start_terminal_recording(
+    session_id: String,
+    path: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let state_lock = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let session = state_lock
+        .pty_sessions
+        .get(&session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+    let mut recorder = session
+        .recorder
+        .lock()
+        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
+    if let Some(existing) = recorder.as_ref() {
+        return Err(format!("Already recording to {}", existing.path().display()));
+    }
+
+    let size = session
+        .master
+        .get_size()
+        .map_err(|e| format!("Failed to get terminal size: {}", e))?;
+
+    let path = path.map(PathBuf::from).unwrap_or_else(|| {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        recordings_dir().join(format!("{}-{}.cast", stamp, &session_id[..8.min(session_id.len())]))
+    });
+
+    let new_recorder = CastRecorder::create(path, size.cols, size.rows)?;
+    let path_str = new_recorder.path().to_string_lossy().to_string();
+    *recorder = Some(new_recorder);
+
+    Ok(path_str)
+}
+
+/// Stop recording a terminal. Returns the path of the finished file, if any.
+#[tauri::command]
+pub fn stop_terminal_recording(
+    session_id: String,
+    state: tauri::State<AppState>,
+) -> Result<Option<String>, String> {
+    let recorder = {
+        let state_lock = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        let session = state_lock
+            .pty_sessions
+            .get(&session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        let taken = session
+            .recorder
+            .lock()
+            .map_err(|e| format!("Failed to lock recorder: {}", e))?
+            .take();
+        taken
+    };
+
+    match recorder {
+        Some(recorder) => recorder.finish().map(|p| Some(p.to_string_lossy().to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Play a `.cast` file back through `terminal-output` events under a fresh
+/// replay id, so an xterm.js instance subscribed to that id renders it.
+/// Returns the replay id and the recorded terminal size.
+#[tauri::command]
+pub fn replay_recording(
+    path: String,
+    speed: Option<f64>,
+    max_idle_secs: Option<f64>,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<serde_json::Value, String> {
+    let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header_line = lines
+        .next()
+        .ok_or_else(|| "Recording is empty".to_string())?
+        .map_err(|e| format!("Failed to read recording: {}", e))?;
+    let header: serde_json::Value = serde_json::from_str(&header_line)
+        .map_err(|e| format!("Invalid asciicast header: {}", e))?;
+    if header.get("version").and_then(|v| v.as_u64()) != Some(2) {
+        return Err("Only asciicast v2 recordings are supported".to_string());
+    }
+
+    let replay_id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .replays
+        .insert(replay_id.clone(), cancel.clone());
+
+    let speed = speed.filter(|s| *s > 0.0).unwrap_or(1.0);
+    let max_idle = max_idle_secs.unwrap_or(DEFAULT_REPLAY_MAX_IDLE_SECS);
+    let replay_id_clone = replay_id.clone();
+    let state_inner = state.inner().clone();
+
+    std::thread::spawn(move || {
+        let mut last_time = 0.0_f64;
+        for line in lines {
+            if cancel.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(line) = line else { break };
+            let Ok((time, kind, data)) = serde_json::from_str::<(f64, String, String)>(&line) else {
+                continue;
+            };
+
+            let delay = ((time - last_time).max(0.0)).min(max_idle) / speed;
+            last_time = time;
+            if delay > 0.0 {
+                std::thread::sleep(Duration::from_secs_f64(delay));
+            }
+
+            match kind.as_str() {
+                "o" => {
+                    let _ = app.emit(
+                        "terminal-output",
+                        serde_json::json!({ "session_id": replay_id_clone, "data": data }),
+                    );
+                }
+                "r" => {
+                    if let Some((cols, rows)) = data.split_once('x') {
+                        let _ = app.emit(
+                            "terminal-replay-resize",
+                            serde_json::json!({
+                                "session_id": replay_id_clone,
+                                "cols": cols.parse::<u16>().unwrap_or(80),
+                                "rows": rows.parse::<u16>().unwrap_or(24),
+                            }),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Ok(mut st) = state_inner.lock() {
+            st.replays.remove(&replay_id_clone);
+        }
+        let _ = app.emit(
+            "terminal-replay-finished",
+            serde_json::json!({ "session_id": replay_id_clone }),
+        );
+    });
+
+    Ok(serde_json::json!({
+        "session_id": replay_id,
+        "cols": header.get("width").and_then(|v| v.as_u64()).unwrap_or(80),
+        "rows": header.get("height").and_then(|v| v.as_u64()).unwrap_or(24),
+    }))
+}
+
+#[tauri::command]
+pub fn stop_replay(session_id: String, state: tauri::State<AppState>) -> Result<(), String> {
+    if let Some(cancel) = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .replays
+        .remove(&session_id)
+    {
+        cancel.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use crate::state::AppState;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Terminal state kept next to each session, fed with the same bytes the
+/// webview gets. Only the visible grid is kept; history lives in the scrollback.
+pub type ScreenModel = Arc<Mutex<vt100::Parser>>;
+
+pub fn new_screen(rows: u16, cols: u16) -> ScreenModel {
+    Arc::new(Mutex::new(vt100::Parser::new(rows, cols, 0)))
+}
+
+pub fn feed(screen: &ScreenModel, data: &[u8]) {
+    if let Ok(mut parser) = screen.lock() {
+        parser.process(data);
+    }
+}
+
+pub fn resize(screen: &ScreenModel, rows: u16, cols: u16) {
+    if let Ok(mut parser) = screen.lock() {
+        parser.screen_mut().set_size(rows, cols);
+    }
+}
+
+/// Palette index (0-255) or `[r, g, b]`; absent means the terminal default
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ScreenColor {
+    Indexed(u8),
+    Rgb([u8; 3]),
+}
+
+fn color(c: vt100::Color) -> Option<ScreenColor> {
+    match c {
+        vt100::Color::Default => None,
+        vt100::Color::Idx(i) => Some(ScreenColor::Indexed(i)),
+        vt100::Color::Rgb(r, g, b) => Some(ScreenColor::Rgb([r, g, b])),
+    }
+}
+
+/// A run of cells on one row that share the same attributes
+#[derive(Serialize, Clone, Debug)]
+pub struct StyledSpan {
+    pub col: u16,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<ScreenColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<ScreenColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScreenSnapshot {
+    pub rows: u16,
+    pub cols: u16,
+    /// Zero-based (row, col)
+    pub cursor: (u16, u16),
+    pub cursor_visible: bool,
+    pub alternate_screen: bool,
+    /// One string per row, trailing blanks trimmed
+    pub lines: Vec<String>,
+    /// Per-row attribute runs, only when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spans: Option<Vec<Vec<StyledSpan>>>,
+}
+
+impl StyledSpan {
+    fn starting_at(col: u16, cell: &vt100::Cell) -> Self {
+        StyledSpan {
+            col,
+            text: String::new(),
+            fg: color(cell.fgcolor()),
+            bg: color(cell.bgcolor()),
+            bold: cell.bold(),
+            dim: cell.dim(),
+            italic: cell.italic(),
+            underline: cell.underline(),
+            inverse: cell.inverse(),
+        }
+    }
+
+    fn same_style(&self, other: &StyledSpan) -> bool {
+        (self.fg, self.bg, self.bold, self.dim, self.italic, self.underline, self.inverse)
+            == (other.fg, other.bg, other.bold, other.dim, other.italic, other.underline, other.inverse)
+    }
+}
+
+fn row_spans(screen: &vt100::Screen, row: u16, cols: u16) -> Vec<StyledSpan> {
+    let mut spans: Vec<StyledSpan> = Vec::new();
+    for col in 0..cols {
+        let Some(cell) = screen.cell(row, col) else { continue };
+        if cell.is_wide_continuation() {
+            continue;
+        }
+        let text = if cell.has_contents() { cell.contents() } else { " " };
+        let next = StyledSpan::starting_at(col, cell);
+        match spans.last_mut() {
+            Some(last) if last.same_style(&next) => last.text.push_str(text),
+            _ => {
+                let mut next = next;
+                next.text.push_str(text);
+                spans.push(next);
+            }
+        }
+    }
+    // Trailing unstyled blanks carry no information
+    if let Some(last) = spans.last_mut() {
+        if last.bg.is_none() && !last.inverse && !last.underline {
+            let trimmed = last.text.trim_end().len();
+            last.text.truncate(trimmed);
+            if last.text.is_empty() {
+                spans.pop();
+            }
+        }
+    }
+    spans
+}
+
+pub fn snapshot(screen: &ScreenModel, attributes: bool) -> Result<ScreenSnapshot, String> {
+    let parser = screen
+        .lock()
+        .map_err(|e| format!("Failed to lock screen: {}", e))?;
+    let screen = parser.screen();
+    let (rows, cols) = screen.size();
+    let lines = screen
+        .rows(0, cols)
+        .map(|line| line.trim_end().to_string())
+        .collect();
+    let spans = attributes.then(|| (0..rows).map(|row| row_spans(screen, row, cols)).collect());
+    Ok(ScreenSnapshot {
+        rows,
+        cols,
+        cursor: screen.cursor_position(),
+        cursor_visible: !screen.hide_cursor(),
+        alternate_screen: screen.alternate_screen(),
+        lines,
+        spans,
+    })
+}
+
+/// What a terminal currently shows, as text. `attributes` adds colour and
+/// style runs per row.
+#[tauri::command]
+pub fn get_terminal_screen(
+    session_id: String,
+    attributes: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<ScreenSnapshot, String> {
+    let state_lock = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let screen = if let Some(session) = state_lock.pty_sessions.get(&session_id) {
+        session.screen.clone()
+    } else if let Some(attachment) = state_lock.daemon_sessions.get(&session_id) {
+        attachment.screen.clone()
+    } else {
+        return Err(format!("Session not found: {}", session_id));
+    };
+    drop(state_lock);
+    snapshot(&screen, attributes.unwrap_or(false))
+}
@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+/// Default scrollback kept per session (1 MiB of raw PTY output)
+pub const DEFAULT_SCROLLBACK_BYTES: usize = 1024 * 1024;
+
+/// Bounded byte ring buffer holding the most recent output of a PTY session.
+/// Raw bytes are stored (not decoded text) so a reattaching terminal can replay
+/// escape sequences and redraw exactly what the shell produced.
+pub struct ScrollbackBuffer {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    total_written: u64,
+}
+
+impl ScrollbackBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: VecDeque::with_capacity(capacity.min(64 * 1024)),
+            capacity,
+            total_written: 0,
+        }
+    }
+
+    /// Append output, dropping the oldest bytes once capacity is exceeded
+    pub fn push(&mut self, data: &[u8]) {
+        self.total_written += data.len() as u64;
+
+        if data.len() >= self.capacity {
+            self.buf.clear();
+            self.buf.extend(&data[data.len() - self.capacity..]);
+            return;
+        }
+
+        let overflow = (self.buf.len() + data.len()).saturating_sub(self.capacity);
+        if overflow > 0 {
+            self.buf.drain(..overflow);
+        }
+        self.buf.extend(data);
+    }
+
+    /// Copy out the last `max_bytes` bytes (or everything when `None`).
+    /// When the cut lands inside a multi-byte UTF-8 character, the partial
+    /// leading bytes are skipped so the snapshot decodes cleanly.
+    pub fn snapshot(&self, max_bytes: Option<usize>) -> Vec<u8> {
+        let len = self.buf.len();
+        let mut start = len - max_bytes.unwrap_or(len).min(len);
+
+        if start > 0 || self.total_written > len as u64 {
+            while start < len && is_utf8_continuation(self.buf[start]) {
+                start += 1;
+            }
+        }
+
+        self.buf.range(start..).copied().collect()
+    }
+
+    /// Total bytes ever written, including those already evicted
+    pub fn total_written(&self) -> u64 {
+        self.total_written
+    }
+}
+
+fn is_utf8_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
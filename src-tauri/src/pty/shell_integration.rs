@@ -0,0 +1,409 @@
+use crate::pty::manager::SpawnSpec;
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+/// Longest OSC payload we buffer; anything bigger isn't a marker we care about
+const MAX_OSC_LEN: usize = 8192;
+
+/// Finished commands kept per session
+const MAX_HISTORY: usize = 500;
+
+const BASH_INTEGRATION: &str = r#"# Lirah shell integration for bash (generated, do not edit)
+if [ "$LIRAH_LOGIN_SHELL" = 1 ]; then
+    [ -r /etc/profile ] && . /etc/profile
+    for __lirah_f in "$HOME/.bash_profile" "$HOME/.bash_login" "$HOME/.profile"; do
+        [ -r "$__lirah_f" ] && { . "$__lirah_f"; break; }
+    done
+    unset __lirah_f
+else
+    [ -r "$HOME/.bashrc" ] && . "$HOME/.bashrc"
+fi
+unset LIRAH_LOGIN_SHELL
+
+__lirah_urlencode() {
+    local LC_ALL=C s="$1" out="" c i
+    for ((i = 0; i < ${#s}; i++)); do
+        c=${s:i:1}
+        case "$c" in
+            [a-zA-Z0-9.~_/-]) out+="$c" ;;
+            *) printf -v c '%%%02X' "'$c"; out+="$c" ;;
+        esac
+    done
+    printf '%s' "$out"
+}
+
+__lirah_cmdline() {
+    local line
+    line=$(HISTTIMEFORMAT= builtin history 1)
+    [[ $line =~ ^[[:space:]]*[0-9]+[*]?[[:space:]]+(.*)$ ]] && line=${BASH_REMATCH[1]}
+    __lirah_urlencode "$line"
+}
+
+__lirah_precmd() {
+    local status=$?
+    printf '\e]133;D;%s\a' "$status"
+    printf '\e]7;file://%s%s\a' "$HOSTNAME" "$(__lirah_urlencode "$PWD")"
+    printf '\e]133;A\a'
+    return $status
+}
+
+if [[ $PROMPT_COMMAND != *__lirah_precmd* ]]; then
+    PROMPT_COMMAND="__lirah_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+    PS1="${PS1}\[\e]133;B\a\]"
+    PS0='\e]133;C;cmdline_url=$(__lirah_cmdline)\a'"${PS0}"
+fi
+"#;
+
+const ZSH_ZSHENV: &str = r#"# Lirah shell integration for zsh (generated, do not edit)
+# Hand ZDOTDIR back to the user before their own startup files run
+if [[ -n $LIRAH_USER_ZDOTDIR ]]; then
+    ZDOTDIR=$LIRAH_USER_ZDOTDIR
+else
+    unset ZDOTDIR
+fi
+unset LIRAH_USER_ZDOTDIR
+[[ -r ${ZDOTDIR:-$HOME}/.zshenv ]] && . ${ZDOTDIR:-$HOME}/.zshenv
+
+if [[ -o interactive ]]; then
+    __lirah_urlencode() {
+        local LC_ALL=C out="" c
+        for c in ${(s::)1}; do
+            case $c in
+                [a-zA-Z0-9.~_/-]) out+=$c ;;
+                *) out+=$(printf '%%%02X' "'$c") ;;
+            esac
+        done
+        print -rn -- $out
+    }
+    __lirah_precmd() {
+        local ret=$?
+        if [[ -n $__lirah_running ]]; then
+            printf '\e]133;D;%s\a' $ret
+            unset __lirah_running
+        fi
+        printf '\e]7;file://%s%s\a' "$HOST" "$(__lirah_urlencode "$PWD")"
+        printf '\e]133;A\a'
+        [[ $PS1 == *'133;B'* ]] || PS1="$PS1%{"$'\e]133;B\a'"%}"
+    }
+    __lirah_preexec() {
+        __lirah_running=1
+        printf '\e]133;C;cmdline_url=%s\a' "$(__lirah_urlencode "$1")"
+    }
+    # First in line so it sees the command's exit status
+    precmd_functions=(__lirah_precmd $precmd_functions)
+    preexec_functions+=(__lirah_preexec)
+fi
+"#;
+
+const FISH_INTEGRATION: &str = r#"# Lirah shell integration for fish (generated, do not edit)
+function __lirah_prompt --on-event fish_prompt
+    printf '\e]7;file://%s%s\a' (hostname) (string escape --style=url -- $PWD)
+    printf '\e]133;A\a'
+end
+function __lirah_preexec --on-event fish_preexec
+    printf '\e]133;C;cmdline_url=%s\a' (string escape --style=url -- "$argv")
+end
+function __lirah_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+"#;
+
+fn integration_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("shell-integration")
+}
+
+fn write_if_changed(path: &std::path::Path, content: &str) -> Result<(), String> {
+    if std::fs::read_to_string(path).ok().as_deref() == Some(content) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Rewrite an interactive shell spec so the shell loads our OSC 7/133
+/// hooks after the user's own startup files. Specs with arguments, or for
+/// programs other than bash/zsh/fish, are left alone.
+pub fn inject(spec: &SpawnSpec, program: String, login: bool) -> Result<SpawnSpec, String> {
+    let mut spec = spec.clone();
+    if !spec.args.is_empty() {
+        return Ok(spec);
+    }
+    let dir = integration_dir();
+    let shell = std::path::Path::new(&program)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match shell.as_str() {
+        "bash" => {
+            let path = dir.join("bash-init.sh");
+            write_if_changed(&path, BASH_INTEGRATION)?;
+            // --init-file only applies to non-login shells; the script does the login part
+            spec.args = vec!["--init-file".to_string(), path.to_string_lossy().to_string()];
+            spec.login = Some(false);
+            if login {
+                spec.env.insert("LIRAH_LOGIN_SHELL".to_string(), "1".to_string());
+            }
+        }
+        "zsh" => {
+            let zdotdir = dir.join("zsh");
+            write_if_changed(&zdotdir.join(".zshenv"), ZSH_ZSHENV)?;
+            if let Some(user_zdotdir) = spec.env.get("ZDOTDIR").cloned().or_else(|| std::env::var("ZDOTDIR").ok()) {
+                spec.env.insert("LIRAH_USER_ZDOTDIR".to_string(), user_zdotdir);
+            }
+            spec.env.insert("ZDOTDIR".to_string(), zdotdir.to_string_lossy().to_string());
+        }
+        "fish" => {
+            let path = dir.join("lirah.fish");
+            write_if_changed(&path, FISH_INTEGRATION)?;
+            spec.args = vec!["--init-command".to_string(), format!("source '{}'", path.display())];
+        }
+        _ => return Ok(spec),
+    }
+    spec.program = Some(program);
+    Ok(spec)
+}
+
+/// What the PTY output told us about the shell
+#[derive(Debug, PartialEq, Eq)]
+enum ShellEvent {
+    Cwd(String),
+    CommandStarted(Option<String>),
+    CommandFinished(Option<i32>),
+}
+
+/// Streaming scanner for OSC sequences (`ESC ] ... BEL` or `ESC ] ... ESC \`),
+/// which can be split across PTY reads
+#[derive(Default)]
+struct OscParser {
+    state: OscState,
+    payload: Vec<u8>,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum OscState {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    OscEscape,
+}
+
+impl OscParser {
+    fn feed(&mut self, data: &[u8], events: &mut Vec<ShellEvent>) {
+        for &b in data {
+            self.state = match (self.state, b) {
+                (OscState::Ground, 0x1b) => OscState::Escape,
+                (OscState::Ground, _) => OscState::Ground,
+                (OscState::Escape, b']') => {
+                    self.payload.clear();
+                    OscState::Osc
+                }
+                (OscState::Escape, 0x1b) => OscState::Escape,
+                (OscState::Escape, _) => OscState::Ground,
+                (OscState::Osc, 0x07) => {
+                    events.extend(parse_osc(&self.payload));
+                    OscState::Ground
+                }
+                (OscState::Osc, 0x1b) => OscState::OscEscape,
+                (OscState::Osc, _) if self.payload.len() >= MAX_OSC_LEN => OscState::Ground,
+                (OscState::Osc, b) => {
+                    self.payload.push(b);
+                    OscState::Osc
+                }
+                (OscState::OscEscape, b'\\') => {
+                    events.extend(parse_osc(&self.payload));
+                    OscState::Ground
+                }
+                // Some other escape interrupted the OSC
+                (OscState::OscEscape, b']') => {
+                    self.payload.clear();
+                    OscState::Osc
+                }
+                (OscState::OscEscape, _) => OscState::Ground,
+            };
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(v) = u8::from_str_radix(hex, 16) {
+                out.push(v);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn parse_osc(payload: &[u8]) -> Option<ShellEvent> {
+    let payload = String::from_utf8_lossy(payload);
+    let (code, rest) = payload.split_once(';')?;
+    match code {
+        // file://host/path
+        "7" => {
+            let after_scheme = rest.strip_prefix("file://")?;
+            let path = &after_scheme[after_scheme.find('/')?..];
+            Some(ShellEvent::Cwd(percent_decode(path)))
+        }
+        "133" => {
+            let mut parts = rest.split(';');
+            match parts.next()? {
+                "C" => {
+                    let cmdline = parts.find_map(|p| {
+                        p.strip_prefix("cmdline_url=")
+                            .map(percent_decode)
+                            .or_else(|| p.strip_prefix("cmdline=").map(String::from))
+                    });
+                    Some(ShellEvent::CommandStarted(cmdline.filter(|c| !c.is_empty())))
+                }
+                "D" => Some(ShellEvent::CommandFinished(parts.next().and_then(|c| c.parse().ok()))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CommandRecord {
+    pub command: Option<String>,
+    pub cwd: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Unix timestamp in milliseconds
+    pub started_at: u64,
+    pub duration_ms: u64,
+}
+
+struct RunningCommand {
+    command: Option<String>,
+    cwd: Option<String>,
+    started_at: u64,
+    started: Instant,
+}
+
+/// Per-session view of the shell built from OSC 7/133 markers
+#[derive(Default)]
+pub struct ShellTracker {
+    parser: OscParser,
+    cwd: Option<String>,
+    running: Option<RunningCommand>,
+    history: VecDeque<CommandRecord>,
+}
+
+impl ShellTracker {
+    /// Last directory the shell reported, if it has integration
+    pub fn cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    pub fn history(&self) -> &VecDeque<CommandRecord> {
+        &self.history
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Scan a chunk of terminal output and emit `cwd-changed` / `command-finished`
+pub fn observe(app: &AppHandle, session_id: &str, tracker: &Mutex<ShellTracker>, data: &[u8]) {
+    // Cheap check: most output has no escape sequences at all
+    let Ok(mut tracker) = tracker.lock() else { return };
+    if tracker.parser.state == OscState::Ground && !data.contains(&0x1b) {
+        return;
+    }
+    let mut events = Vec::new();
+    tracker.parser.feed(data, &mut events);
+
+    for event in events {
+        match event {
+            ShellEvent::Cwd(cwd) => {
+                if tracker.cwd.as_deref() != Some(cwd.as_str()) {
+                    let _ = app.emit(
+                        "cwd-changed",
+                        serde_json::json!({ "session_id": session_id, "cwd": cwd }),
+                    );
+                    tracker.cwd = Some(cwd);
+                }
+            }
+            ShellEvent::CommandStarted(command) => {
+                tracker.running = Some(RunningCommand {
+                    command,
+                    cwd: tracker.cwd.clone(),
+                    started_at: now_ms(),
+                    started: Instant::now(),
+                });
+            }
+            // bash reports D before every prompt, even when nothing ran
+            ShellEvent::CommandFinished(exit_code) => {
+                let Some(running) = tracker.running.take() else { continue };
+                let record = CommandRecord {
+                    command: running.command,
+                    cwd: running.cwd,
+                    exit_code,
+                    started_at: running.started_at,
+                    duration_ms: running.started.elapsed().as_millis() as u64,
+                };
+                let _ = app.emit(
+                    "command-finished",
+                    serde_json::json!({ "session_id": session_id, "command": &record }),
+                );
+                if tracker.history.len() >= MAX_HISTORY {
+                    tracker.history.pop_front();
+                }
+                tracker.history.push_back(record);
+            }
+        }
+    }
+}
+
+/// Commands a terminal has finished, oldest first (needs shell integration)
+#[tauri::command]
+pub fn get_command_history(
+    session_id: String,
+    limit: Option<usize>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<CommandRecord>, String> {
+    let state_lock = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let tracker = if let Some(session) = state_lock.pty_sessions.get(&session_id) {
+        session.shell.clone()
+    } else if let Some(attachment) = state_lock.daemon_sessions.get(&session_id) {
+        attachment.shell.clone()
+    } else {
+        return Err(format!("Session not found: {}", session_id));
+    };
+    drop(state_lock);
+
+    let tracker = tracker
+        .lock()
+        .map_err(|e| format!("Failed to lock shell tracker: {}", e))?;
+    let history = tracker.history();
+    let skip = history.len().saturating_sub(limit.unwrap_or(history.len()));
+    Ok(history.iter().skip(skip).cloned().collect())
+}
@@ -0,0 +1,222 @@
+use crate::pty::process_tree::{descendants, is_descendant, session_root_pid};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, Signal, System};
+
+/// How often an escalation step checks whether the target is gone
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerminalSignal {
+    #[serde(rename = "SIGINT")]
+    Int,
+    #[serde(rename = "SIGTERM")]
+    Term,
+    #[serde(rename = "SIGHUP")]
+    Hup,
+    #[serde(rename = "SIGSTOP")]
+    Stop,
+    #[serde(rename = "SIGCONT")]
+    Cont,
+    #[serde(rename = "SIGKILL")]
+    Kill,
+}
+
+impl TerminalSignal {
+    fn to_sysinfo(self) -> Signal {
+        match self {
+            TerminalSignal::Int => Signal::Interrupt,
+            TerminalSignal::Term => Signal::Term,
+            TerminalSignal::Hup => Signal::Hangup,
+            TerminalSignal::Stop => Signal::Stop,
+            TerminalSignal::Cont => Signal::Continue,
+            TerminalSignal::Kill => Signal::Kill,
+        }
+    }
+}
+
+/// Send `signal`, then give the process `wait_ms` to exit before the next step
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct EscalationStep {
+    pub signal: TerminalSignal,
+    #[serde(default)]
+    pub wait_ms: u64,
+}
+
+/// SIGINT, wait 3s, SIGTERM, wait 5s, SIGKILL
+pub const DEFAULT_ESCALATION: &[EscalationStep] = &[
+    EscalationStep { signal: TerminalSignal::Int, wait_ms: 3000 },
+    EscalationStep { signal: TerminalSignal::Term, wait_ms: 5000 },
+    EscalationStep { signal: TerminalSignal::Kill, wait_ms: 1000 },
+];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SignalOutcome {
+    pub pid: u32,
+    pub name: String,
+    /// Signals actually sent, in order
+    pub sent: Vec<TerminalSignal>,
+    /// The step after which the process was gone, if it exited
+    pub stopped_by: Option<TerminalSignal>,
+    pub exited: bool,
+}
+
+/// Identity of a process that survives pid reuse
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ProcessKey {
+    pid: Pid,
+    start_time: u64,
+}
+
+fn refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::nothing()
+}
+
+/// Resolve `target` (a pid or a process name) to one process under `root`.
+/// Names match case-insensitively; if several unrelated processes match, the
+/// caller has to pick a pid instead of us guessing.
+fn resolve_target(sys: &System, root: Pid, target: &str) -> Result<ProcessKey, String> {
+    let in_session = |pid: Pid| pid == root || is_descendant(sys, pid, root);
+
+    let pid = if let Ok(raw) = target.trim().parse::<u32>() {
+        let pid = Pid::from_u32(raw);
+        if sys.process(pid).is_none() || !in_session(pid) {
+            return Err(format!("Process {} is not running in this terminal", raw));
+        }
+        pid
+    } else {
+        let name = target.trim().to_lowercase();
+        let mut matches: Vec<Pid> = std::iter::once(root)
+            .chain(descendants(sys, root))
+            .filter(|pid| {
+                sys.process(*pid)
+                    .is_some_and(|p| p.name().to_string_lossy().to_lowercase() == name)
+            })
+            .collect();
+        // A match below another match is the same program (e.g. node workers)
+        let all = matches.clone();
+        matches.retain(|pid| !all.iter().any(|other| other != pid && is_descendant(sys, *pid, *other)));
+        match matches.as_slice() {
+            [] => return Err(format!("No process named '{}' in this terminal", target)),
+            [pid] => *pid,
+            many => {
+                let pids: Vec<String> = many.iter().map(|p| p.to_string()).collect();
+                return Err(format!(
+                    "Several processes named '{}' ({}); pass a pid instead",
+                    target,
+                    pids.join(", ")
+                ));
+            }
+        }
+    };
+
+    let start_time = sys.process(pid).map(|p| p.start_time()).unwrap_or(0);
+    Ok(ProcessKey { pid, start_time })
+}
+
+/// Gone, a zombie, or a different process that reused the pid
+fn has_exited(sys: &mut System, key: ProcessKey) -> bool {
+    sys.refresh_processes_specifics(ProcessesToUpdate::Some(&[key.pid]), true, refresh_kind());
+    match sys.process(key.pid) {
+        Some(p) => p.start_time() != key.start_time || matches!(p.status(), ProcessStatus::Zombie | ProcessStatus::Dead),
+        None => true,
+    }
+}
+
+fn send(sys: &System, keys: &[ProcessKey], signal: TerminalSignal) -> Result<(), String> {
+    for key in keys {
+        let Some(process) = sys.process(key.pid).filter(|p| p.start_time() == key.start_time) else {
+            continue;
+        };
+        match process.kill_with(signal.to_sysinfo()) {
+            Some(_) => {}
+            None => return Err(format!("{:?} is not supported on this platform", signal)),
+        }
+    }
+    Ok(())
+}
+
+/// Deliver `steps` to a process (and optionally everything below it),
+/// stopping as soon as the process has exited
+pub fn deliver(
+    root_pid: u32,
+    target: &str,
+    steps: &[EscalationStep],
+    include_children: bool,
+) -> Result<SignalOutcome, String> {
+    let root = Pid::from_u32(root_pid);
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh_kind());
+
+    let key = resolve_target(&sys, root, target)?;
+    let name = sys
+        .process(key.pid)
+        .map(|p| p.name().to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Children first so a dying parent can't leave them reparented and missed
+    let mut keys: Vec<ProcessKey> = if include_children {
+        descendants(&sys, key.pid)
+            .into_iter()
+            .rev()
+            .filter_map(|pid| sys.process(pid).map(|p| ProcessKey { pid, start_time: p.start_time() }))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    keys.push(key);
+
+    let mut outcome = SignalOutcome {
+        pid: key.pid.as_u32(),
+        name,
+        sent: Vec::new(),
+        stopped_by: None,
+        exited: false,
+    };
+
+    for step in steps {
+        send(&sys, &keys, step.signal)?;
+        outcome.sent.push(step.signal);
+        eprintln!("[pty] Sent {:?} to {} ({})", step.signal, outcome.pid, outcome.name);
+
+        let deadline = Instant::now() + Duration::from_millis(step.wait_ms);
+        loop {
+            if has_exited(&mut sys, key) {
+                outcome.exited = true;
+                outcome.stopped_by = Some(step.signal);
+                return Ok(outcome);
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+    Ok(outcome)
+}
+
+/// Signal a process running in a terminal, by pid or name. With `escalation`
+/// the steps run in order until the process exits; otherwise `signal`
+/// (default SIGTERM) is sent once. SIGSTOP/SIGCONT with `include_children`
+/// pause and resume a whole agent.
+#[tauri::command(async)]
+pub fn signal_terminal_process(
+    session_id: String,
+    pid_or_name: String,
+    signal: Option<TerminalSignal>,
+    escalation: Option<Vec<EscalationStep>>,
+    include_children: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<SignalOutcome, String> {
+    let root = session_root_pid(state.inner(), &session_id)?;
+    let steps = match escalation {
+        Some(steps) if steps.is_empty() => return Err("Escalation policy has no steps".to_string()),
+        Some(steps) => steps,
+        None => vec![EscalationStep {
+            signal: signal.unwrap_or(TerminalSignal::Term),
+            wait_ms: 0,
+        }],
+    };
+    deliver(root, &pid_or_name, &steps, include_children.unwrap_or(false))
+}
@@ -0,0 +1,208 @@
+use crate::fs::directory::{DirectoryEntry, RecursiveDirectoryEntry};
+use crate::ignore_dirs::IGNORE_DIRS;
+use crate::remote::ssh;
+use crate::remote::types::{RemoteSession, SshConfig, SshProfile};
+use crate::state::AppState;
+use std::path::PathBuf;
+use tauri::ipc::JavaScriptChannelId;
+use tauri::{AppHandle, Webview};
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("ssh-hosts.json")
+}
+
+fn load_config() -> SshConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &SshConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[tauri::command]
+pub fn list_ssh_profiles() -> Result<Vec<SshProfile>, String> {
+    let mut profiles = load_config().profiles;
+    profiles.sort_by_key(|p| p.name.to_lowercase());
+    Ok(profiles)
+}
+
+/// Create a profile (empty `id`) or replace an existing one
+#[tauri::command]
+pub fn save_ssh_profile(mut profile: SshProfile) -> Result<SshProfile, String> {
+    ssh::validate(&profile)?;
+    if profile.id.is_empty() {
+        profile.id = uuid::Uuid::new_v4().to_string();
+    }
+    let mut config = load_config();
+    match config.profiles.iter_mut().find(|p| p.id == profile.id) {
+        Some(existing) => *existing = profile.clone(),
+        None => config.profiles.push(profile.clone()),
+    }
+    save_config(&config)?;
+    Ok(profile)
+}
+
+/// Delete a profile. Returns false if there was none with that id.
+#[tauri::command]
+pub fn remove_ssh_profile(id: String) -> Result<bool, String> {
+    let mut config = load_config();
+    let before = config.profiles.len();
+    config.profiles.retain(|p| p.id != id);
+    if config.profiles.len() == before {
+        return Ok(false);
+    }
+    save_config(&config)?;
+    Ok(true)
+}
+
+/// Open a terminal on a saved host. Authentication happens in the terminal
+/// itself (password, key passphrase, host key prompt), after which cwd and
+/// file requests reuse the connection.
+#[tauri::command]
+pub fn spawn_remote_terminal(
+    profile_id: String,
+    rows: u16,
+    cols: u16,
+    on_output: Option<JavaScriptChannelId>,
+    webview: Webview,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<serde_json::Value, String> {
+    let profile = load_config()
+        .profiles
+        .into_iter()
+        .find(|p| p.id == profile_id)
+        .ok_or_else(|| format!("SSH profile not found: {}", profile_id))?;
+    ssh::validate(&profile)?;
+    ssh::ensure_control_dir()?;
+
+    let pid_key = uuid::Uuid::new_v4().simple().to_string();
+    let spec = crate::pty::manager::SpawnSpec {
+        program: Some("ssh".to_string()),
+        args: ssh::terminal_args(&profile, &pid_key),
+        login: Some(false),
+        ..Default::default()
+    };
+    let remote = serde_json::json!({ "profile_id": profile.id, "host": profile.host });
+    let mut result = crate::pty::commands::spawn_local_terminal(
+        rows,
+        cols,
+        false,
+        false,
+        None,
+        spec,
+        Default::default(),
+        on_output.map(|id| id.channel_on(webview)),
+        Some(RemoteSession { profile, pid_key }),
+        app,
+        state.inner(),
+    )?;
+    result["remote"] = remote;
+    Ok(result)
+}
+
+fn remote_session(state: &AppState, session_id: &str) -> Result<RemoteSession, String> {
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .remote_sessions
+        .get(session_id)
+        .cloned()
+        .ok_or_else(|| format!("Not a remote session: {}", session_id))
+}
+
+/// The remote state of `session_id`, if it is an ssh terminal
+pub fn find_remote(state: &AppState, session_id: Option<&str>) -> Option<RemoteSession> {
+    remote_session(state, session_id?).ok()
+}
+
+/// List a directory on a remote terminal's host over SFTP, sorted like
+/// `read_directory`. Without `path`, the remote shell's cwd.
+#[tauri::command(async)]
+pub fn read_remote_directory(
+    session_id: String,
+    path: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DirectoryEntry>, String> {
+    list_directory(&remote_session(state.inner(), &session_id)?, path)
+}
+
+pub fn list_directory(remote: &RemoteSession, path: Option<String>) -> Result<Vec<DirectoryEntry>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => ssh::remote_cwd(remote)?,
+    };
+
+    let base = path.trim_end_matches('/');
+    let mut entries: Vec<DirectoryEntry> = ssh::list_directory(&remote.profile, &path)?
+        .into_iter()
+        .map(|(name, is_dir)| {
+            let path = format!("{}/{}", base, name);
+            DirectoryEntry { name, path, is_dir }
+        })
+        .collect();
+    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+    Ok(entries)
+}
+
+/// The remote counterpart of `read_directory_recursive`, in the same shape
+pub fn walk_directory(
+    remote: &RemoteSession,
+    path: Option<String>,
+    max_depth: usize,
+    max_files: usize,
+) -> Result<Vec<RecursiveDirectoryEntry>, String> {
+    let root = match path {
+        Some(path) => path,
+        None => ssh::remote_cwd(remote)?,
+    };
+    let base = root.trim_end_matches('/');
+    let mut entries: Vec<RecursiveDirectoryEntry> = ssh::walk_directory(&remote.profile, &root, max_depth, max_files, IGNORE_DIRS)?
+        .into_iter()
+        .map(|(rel, is_dir)| {
+            let (parent, name) = match rel.rsplit_once('/') {
+                Some((parent, name)) => (format!("{}/{}", base, parent), name.to_string()),
+                None => (base.to_string(), rel.clone()),
+            };
+            RecursiveDirectoryEntry {
+                name,
+                path: format!("{}/{}", base, rel),
+                is_dir,
+                depth: rel.split('/').count(),
+                parent_path: parent,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+    Ok(entries)
+}
+
+/// Read a text file on a remote terminal's host over SFTP
+#[tauri::command(async)]
+pub fn read_remote_file(
+    session_id: String,
+    path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let remote = remote_session(state.inner(), &session_id)?;
+    ssh::read_file(&remote.profile, &path)
+}
@@ -0,0 +1,22 @@
+//! Remote terminals: `ssh` run inside a local PTY against a saved host
+//! profile, with the cwd queried on the remote and the file tree read over
+//! SFTP so the sidebar keeps working.
+//!
+//! - `types`: host profiles, their config file and per-session remote state
+//! - `ssh`: ssh/sftp argument building, remote cwd lookup and SFTP listing
+//! - `commands`: Tauri commands for profiles, spawning and remote file access
+//!
+//! Profiles are stored in `~/.lirah/ssh-hosts.json`. Every connection to a
+//! host shares one ControlMaster socket under `~/.lirah/ssh`, so the cwd and
+//! SFTP requests ride on the terminal's already authenticated connection.
+//! Both are in the app state dir that sandboxed terminals can't see: the
+//! profiles' ssh options run commands, and the sockets are logged-in shells.
+
+pub mod commands;
+pub mod ssh;
+pub mod types;
+
+pub use commands::{
+    list_ssh_profiles, read_remote_directory, read_remote_file, remove_ssh_profile, save_ssh_profile,
+    spawn_remote_terminal,
+};
@@ -0,0 +1,316 @@
+use crate::remote::types::{RemoteSession, SshProfile};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// How long a host's master connection outlives its last terminal
+const CONTROL_PERSIST: &str = "10m";
+
+/// Keep background requests from hanging on an unreachable host
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+
+/// Where the remote shell's pid is written, relative to the remote home
+const REMOTE_PID_DIR: &str = ".cache/lirah";
+
+/// ControlMaster sockets, one per host/user/port. They sit in the app state
+/// dir hidden from sandboxes, in a subdirectory named afresh for every run:
+/// Landlock can't stop connect() on a socket whose path is known, but it does
+/// keep the directory from being listed.
+pub fn control_dir() -> PathBuf {
+    static RUN_DIR: OnceLock<String> = OnceLock::new();
+    let run = RUN_DIR.get_or_init(|| uuid::Uuid::new_v4().simple().to_string()[..16].to_string());
+    control_root().join(run)
+}
+
+fn control_root() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("ssh")
+}
+
+/// Create the socket directory, private to the user. Directories of earlier
+/// runs are removed once their masters have exited and taken the sockets along.
+pub fn ensure_control_dir() -> Result<(), String> {
+    let dir = control_dir();
+    if !dir.exists() {
+        if let Ok(entries) = std::fs::read_dir(control_root()) {
+            for entry in entries.flatten() {
+                // Only succeeds on empty directories
+                let _ = std::fs::remove_dir(entry.path());
+            }
+        }
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        for path in [control_root(), dir] {
+            let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700));
+        }
+    }
+    Ok(())
+}
+
+/// Reject values ssh would parse as options instead of a host or user
+pub fn validate(profile: &SshProfile) -> Result<(), String> {
+    let bad = |s: &str| s.is_empty() || s.starts_with('-') || s.chars().any(char::is_whitespace);
+    if bad(&profile.host) {
+        return Err(format!("Invalid host: {:?}", profile.host));
+    }
+    if let Some(user) = &profile.user {
+        if bad(user) || user.contains('@') {
+            return Err(format!("Invalid user: {:?}", user));
+        }
+    }
+    Ok(())
+}
+
+fn destination(profile: &SshProfile) -> String {
+    match &profile.user {
+        Some(user) => format!("{}@{}", user, profile.host),
+        None => profile.host.clone(),
+    }
+}
+
+/// Options shared by ssh and sftp; they differ only in the port flag
+fn connection_args(profile: &SshProfile, port_flag: &str) -> Vec<String> {
+    let mut args = vec![
+        "-o".to_string(),
+        "ControlMaster=auto".to_string(),
+        "-o".to_string(),
+        format!("ControlPath={}/%C", control_dir().display()),
+        "-o".to_string(),
+        format!("ControlPersist={}", CONTROL_PERSIST),
+    ];
+    if let Some(port) = profile.port {
+        args.extend([port_flag.to_string(), port.to_string()]);
+    }
+    if let Some(identity) = &profile.identity_file {
+        args.extend(["-i".to_string(), identity.clone()]);
+    }
+    for option in &profile.options {
+        args.extend(["-o".to_string(), option.clone()]);
+    }
+    args
+}
+
+/// Quote for a POSIX shell
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Like `sh_quote`, but keeps a leading `~/` pointing at the remote home
+fn sh_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("\"$HOME\"/{}", sh_quote(rest)),
+        None if path == "~" => "\"$HOME\"".to_string(),
+        None => sh_quote(path),
+    }
+}
+
+fn pid_file(pid_key: &str) -> String {
+    format!("\"$HOME\"/{}/{}.pid", REMOTE_PID_DIR, pid_key)
+}
+
+/// Arguments for the `ssh` process that runs in the terminal. The remote side
+/// records its pid (the login shell execs in place, keeping it) so the cwd
+/// can be looked up later.
+pub fn terminal_args(profile: &SshProfile, pid_key: &str) -> Vec<String> {
+    let cd = profile
+        .remote_dir
+        .as_deref()
+        .filter(|d| !d.is_empty())
+        .map(|d| format!("cd {} 2>/dev/null; ", sh_path(d)))
+        .unwrap_or_default();
+    let script = format!(
+        "mkdir -p \"$HOME\"/{dir} && echo $$ > {pid}; {cd}exec \"${{SHELL:-/bin/sh}}\" -l",
+        dir = REMOTE_PID_DIR,
+        pid = pid_file(pid_key),
+        cd = cd,
+    );
+
+    let mut args = vec!["-t".to_string()];
+    args.extend(connection_args(profile, "-p"));
+    // Run through sh so the script works whatever the remote login shell is
+    args.extend([destination(profile), format!("sh -c {}", sh_quote(&script))]);
+    args
+}
+
+/// Run a shell script on the host without prompting for anything
+fn run_remote(profile: &SshProfile, script: &str) -> Result<String, String> {
+    let output = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", "-o"])
+        .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS))
+        .args(connection_args(profile, "-p"))
+        .arg(destination(profile))
+        .arg(format!("sh -c {}", sh_quote(script)))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run ssh: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ssh to {} failed: {}",
+            profile.host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Working directory of the remote shell, from /proc or lsof on the host
+pub fn remote_cwd(session: &RemoteSession) -> Result<String, String> {
+    let script = format!(
+        "pid=$(cat {pid}) && {{ readlink /proc/$pid/cwd 2>/dev/null || lsof -a -p $pid -d cwd -Fn 2>/dev/null | sed -n 's/^n//p'; }}",
+        pid = pid_file(&session.pid_key),
+    );
+    let cwd = run_remote(&session.profile, &script)?.trim().to_string();
+    if cwd.is_empty() {
+        return Err(format!("Could not read the remote cwd on {}", session.profile.host));
+    }
+    Ok(cwd)
+}
+
+/// Remove the pid file once the terminal is closed. Best effort, in the
+/// background: the host may already be gone.
+pub fn cleanup(session: RemoteSession) {
+    std::thread::spawn(move || {
+        let _ = run_remote(&session.profile, &format!("rm -f {}", pid_file(&session.pid_key)));
+    });
+}
+
+/// Quote a path for an sftp batch command
+fn sftp_quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Run sftp batch commands; the first failing command aborts the batch
+fn run_sftp(profile: &SshProfile, batch: &str) -> Result<String, String> {
+    let mut child = Command::new("sftp")
+        .args(["-b", "-", "-o", "BatchMode=yes", "-o"])
+        .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS))
+        .args(connection_args(profile, "-P"))
+        .arg(destination(profile))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run sftp: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(batch.as_bytes())
+            .map_err(|e| format!("Failed to write to sftp: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run sftp: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "sftp on {} failed: {}",
+            profile.host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The rest of `line` after skipping `n` whitespace-separated fields
+fn after_fields(line: &str, n: usize) -> Option<&str> {
+    let mut rest = line;
+    for _ in 0..n {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace)?..];
+    }
+    Some(rest.trim_start()).filter(|r| !r.is_empty())
+}
+
+/// One entry of sftp's `ls -la` output as `(name, is_dir)`. Symlinks count
+/// as files whatever they point to, so a link loop can't send a walk in circles.
+fn parse_listing(line: &str) -> Option<(String, bool)> {
+    // Long listing: mode, links, owner, group, size and a three-part date, then the name
+    let name = after_fields(line, 8)?;
+    let name = if line.starts_with('l') {
+        name.split_once(" -> ").map_or(name, |(link, _)| link)
+    } else {
+        name
+    };
+    (name != "." && name != "..").then(|| (name.to_string(), line.starts_with('d')))
+}
+
+/// List a remote directory as `(name, is_dir)` pairs, `.` and `..` excluded
+pub fn list_directory(profile: &SshProfile, path: &str) -> Result<Vec<(String, bool)>, String> {
+    let output = run_sftp(profile, &format!("ls -la {}\n", sftp_quote(path)))?;
+    Ok(output
+        .lines()
+        // Batch mode echoes each command
+        .filter(|line| !line.starts_with("sftp>"))
+        .filter_map(parse_listing)
+        .collect())
+}
+
+/// Walk a remote directory tree over SFTP, one round trip per level, as
+/// `(relative path, is_dir)` pairs. Directories named in `ignore` are skipped
+/// along with their contents, and so are directories that can't be read.
+pub fn walk_directory(
+    profile: &SshProfile,
+    root: &str,
+    max_depth: usize,
+    max_files: usize,
+    ignore: &[&str],
+) -> Result<Vec<(String, bool)>, String> {
+    let base = root.trim_end_matches('/');
+    let mut found = Vec::new();
+    let mut level = vec![String::new()];
+
+    for _ in 0..max_depth {
+        if level.is_empty() || found.len() >= max_files {
+            break;
+        }
+        // `-` keeps one unreadable directory from aborting the batch
+        let batch: String = level
+            .iter()
+            .map(|rel| {
+                let path = if rel.is_empty() { root.to_string() } else { format!("{}/{}", base, rel) };
+                format!("-ls -la {}\n", sftp_quote(&path))
+            })
+            .collect();
+        let output = run_sftp(profile, &batch)?;
+
+        // Each command's echo starts the listing of the next directory in `level`
+        let mut next = Vec::new();
+        let mut current: Option<&String> = None;
+        let mut dirs = level.iter();
+        for line in output.lines() {
+            if line.starts_with("sftp>") {
+                current = dirs.next();
+                continue;
+            }
+            let (Some(parent), Some((name, is_dir))) = (current, parse_listing(line)) else { continue };
+            if is_dir && ignore.contains(&name.as_str()) {
+                continue;
+            }
+            let rel = if parent.is_empty() { name } else { format!("{}/{}", parent, name) };
+            if is_dir {
+                next.push(rel.clone());
+            }
+            found.push((rel, is_dir));
+        }
+        level = next;
+    }
+
+    found.truncate(max_files);
+    Ok(found)
+}
+
+/// Download a remote file and return its contents
+pub fn read_file(profile: &SshProfile, path: &str) -> Result<String, String> {
+    let local = std::env::temp_dir().join(format!("lirah-sftp-{}", uuid::Uuid::new_v4()));
+    let result = run_sftp(
+        profile,
+        &format!("get {} {}\n", sftp_quote(path), sftp_quote(&local.to_string_lossy())),
+    )
+    .and_then(|_| std::fs::read_to_string(&local).map_err(|e| format!("Failed to read file: {}", e)));
+    let _ = std::fs::remove_file(&local);
+    result
+}
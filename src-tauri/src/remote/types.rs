@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// A host remote terminals can be opened on
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SshProfile {
+    /// Assigned when the profile is first saved
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Hostname, address or an alias from `~/.ssh/config`
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Directory the remote shell starts in; `~/` is relative to the remote home
+    #[serde(default)]
+    pub remote_dir: Option<String>,
+    /// Extra `-o` options, e.g. `ServerAliveInterval=30`
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// `~/.lirah/ssh-hosts.json`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SshConfig {
+    #[serde(default)]
+    pub profiles: Vec<SshProfile>,
+}
+
+/// What a terminal running ssh needs for remote cwd and file access
+#[derive(Clone, Debug)]
+pub struct RemoteSession {
+    pub profile: SshProfile,
+    /// Name of the file on the remote holding the shell's pid
+    pub pid_key: String,
+}
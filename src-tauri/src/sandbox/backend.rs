@@ -0,0 +1,294 @@
+use crate::sandbox::policy::SandboxPolicy;
+#[cfg(target_os = "linux")]
+use crate::sandbox::policy::{NetworkMode, PROJECT_STATE_DIR};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxBackendKind {
+    Bwrap,
+    Landlock,
+}
+
+/// Everything a backend needs to confine one terminal
+pub struct SandboxRequest<'a> {
+    pub policy: &'a SandboxPolicy,
+    pub home: Option<&'a str>,
+    pub project_dir: Option<&'a str>,
+    /// Overlay (upper, work) dirs for a copy-on-write project mount
+    pub overlay: Option<(&'a str, &'a str)>,
+    /// Spawn spec variables, applied on top of the passthrough ones
+    pub env: &'a HashMap<String, String>,
+    pub argv: &'a [String],
+}
+
+/// Command line that runs `argv` confined, plus variables the outer process needs
+pub struct SandboxCommand {
+    pub argv: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+pub trait SandboxBackend {
+    fn kind(&self) -> SandboxBackendKind;
+    /// Whether this backend can confine processes on this machine (cached)
+    fn is_available(&self) -> bool;
+    fn supports_overlay(&self) -> bool;
+    fn command(&self, request: &SandboxRequest) -> Result<SandboxCommand, String>;
+}
+
+/// Pick the first usable backend, bwrap before Landlock since it also isolates
+/// PIDs, IPC and hostname. Errors instead of returning nothing, so callers
+/// can't end up running unconfined by accident.
+pub fn select_backend(
+    preferred: Option<SandboxBackendKind>,
+    need_overlay: bool,
+) -> Result<Box<dyn SandboxBackend>, String> {
+    let candidates: Vec<Box<dyn SandboxBackend>> = vec![
+        #[cfg(target_os = "linux")]
+        Box::new(BwrapBackend),
+        #[cfg(target_os = "linux")]
+        Box::new(LandlockBackend),
+    ];
+
+    let mut rejected = Vec::new();
+    for backend in candidates {
+        let name = format!("{:?}", backend.kind()).to_lowercase();
+        if preferred.is_some_and(|p| p != backend.kind()) {
+            continue;
+        }
+        if need_overlay && !backend.supports_overlay() {
+            rejected.push(format!("{}: no overlay support", name));
+        } else if !backend.is_available() {
+            rejected.push(format!("{}: unavailable", name));
+        } else {
+            return Ok(backend);
+        }
+    }
+
+    if rejected.is_empty() {
+        return Err(match preferred {
+            Some(kind) => format!("Sandbox backend {:?} is not supported on this platform", kind),
+            None => "Sandboxing is not supported on this platform".to_string(),
+        });
+    }
+    Err(format!("No sandbox backend available ({})", rejected.join(", ")))
+}
+
+/// Report which backend a sandboxed terminal would use, for the settings UI
+#[tauri::command]
+pub fn get_sandbox_backends() -> Vec<serde_json::Value> {
+    #[cfg(target_os = "linux")]
+    {
+        let backends: [Box<dyn SandboxBackend>; 2] = [Box::new(BwrapBackend), Box::new(LandlockBackend)];
+        backends
+            .iter()
+            .map(|b| {
+                serde_json::json!({
+                    "backend": b.kind(),
+                    "available": b.is_available(),
+                    "overlay": b.supports_overlay(),
+                })
+            })
+            .collect()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// bubblewrap: mount namespace with the policy applied as binds
+#[cfg(target_os = "linux")]
+pub struct BwrapBackend;
+
+#[cfg(target_os = "linux")]
+const BWRAP_PATH: &str = "/usr/bin/bwrap";
+
+#[cfg(target_os = "linux")]
+impl SandboxBackend for BwrapBackend {
+    fn kind(&self) -> SandboxBackendKind {
+        SandboxBackendKind::Bwrap
+    }
+
+    fn is_available(&self) -> bool {
+        static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        *AVAILABLE.get_or_init(|| {
+            if !Path::new(BWRAP_PATH).exists() {
+                return false;
+            }
+            // Ensure bwrap AppArmor profile exists on Ubuntu before probing
+            ensure_bwrap_apparmor();
+            // Spawning bwrap succeeds even when it can't create namespaces,
+            // so actually run something to find out
+            let ok = std::process::Command::new(BWRAP_PATH)
+                .args(["--ro-bind", "/", "/", "--dev", "/dev", "--unshare-pid", "--", "true"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false);
+            eprintln!("[sandbox] bwrap probe: {}", if ok { "ok" } else { "failed" });
+            ok
+        })
+    }
+
+    fn supports_overlay(&self) -> bool {
+        crate::sandbox::overlay::bwrap_supports_overlay()
+    }
+
+    fn command(&self, request: &SandboxRequest) -> Result<SandboxCommand, String> {
+        let mut argv = vec![BWRAP_PATH.to_string()];
+        argv.extend(request.policy.bwrap_args(request.home, request.project_dir, request.overlay)?);
+        // Spec overrides come last so they win over passthrough values
+        for (key, val) in request.env {
+            argv.extend(["--setenv".to_string(), key.clone(), val.clone()]);
+        }
+        argv.push("--".to_string());
+        argv.extend(request.argv.iter().cloned());
+        Ok(SandboxCommand { argv, env: Vec::new() })
+    }
+}
+
+/// Landlock: no namespaces, just filesystem (and on newer kernels TCP)
+/// access rules. Hidden paths and secrets are denied rather than emptied, and
+/// directories above them are list-only, so nothing can be created directly
+/// next to a hidden path. `/tmp` is shared with the host.
+#[cfg(target_os = "linux")]
+pub struct LandlockBackend;
+
+#[cfg(target_os = "linux")]
+impl SandboxBackend for LandlockBackend {
+    fn kind(&self) -> SandboxBackendKind {
+        SandboxBackendKind::Landlock
+    }
+
+    fn is_available(&self) -> bool {
+        crate::sandbox::landlock::abi_version() >= 1
+    }
+
+    fn supports_overlay(&self) -> bool {
+        false
+    }
+
+    fn command(&self, request: &SandboxRequest) -> Result<SandboxCommand, String> {
+        use crate::sandbox::landlock::{abi_version, grant_tree, LandlockSpec, SPEC_ENV};
+        use crate::sandbox::policy::app_state_paths;
+
+        let policy = request.policy;
+        let deny_tcp = policy.network == NetworkMode::None;
+        if deny_tcp && abi_version() < 4 {
+            return Err("Network isolation with Landlock needs Linux 6.7 or newer".to_string());
+        }
+
+        let (state_dir, visible_state) = app_state_paths()?;
+        let mut hidden: Vec<PathBuf> = policy
+            .hidden
+            .iter()
+            .chain(&policy.masked_secrets)
+            .map(PathBuf::from)
+            .collect();
+        // Landlock can't refuse connect() on the daemon socket, but the daemon
+        // refuses confined clients, and unlistable names can't be guessed
+        hidden.push(state_dir);
+        let mut read_only: Vec<PathBuf> = hidden.clone();
+        read_only.extend(policy.read_only.iter().map(PathBuf::from));
+        if let Some(proj) = request.project_dir {
+            read_only.push(Path::new(proj).join(PROJECT_STATE_DIR));
+        }
+
+        let mut spec = LandlockSpec {
+            deny_tcp,
+            argv: request.argv.to_vec(),
+            ..Default::default()
+        };
+        grant_tree(Path::new("/"), &hidden, &mut spec.read, &mut spec.list_only);
+        spec.read.extend(visible_state.iter().map(|p| p.to_string_lossy().to_string()));
+
+        let mut writable: Vec<&str> = vec!["/tmp", "/dev"];
+        writable.extend(request.home);
+        writable.extend(request.project_dir);
+        writable.extend(policy.writable.iter().map(String::as_str));
+        for root in writable {
+            grant_tree(Path::new(root), &read_only, &mut spec.write, &mut spec.list_only);
+        }
+
+        // Same as bwrap's --clearenv plus passthrough
+        for var in &policy.env_passthrough {
+            if let Ok(val) = std::env::var(var) {
+                spec.env.push((var.clone(), val));
+            }
+        }
+        spec.env.push(("TERM".to_string(), "xterm-256color".to_string()));
+        spec.env.extend(request.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let exe = std::env::current_exe()
+            .map_err(|e| format!("Failed to locate lirah executable: {}", e))?;
+        let spec_json = serde_json::to_string(&spec).map_err(|e| e.to_string())?;
+        Ok(SandboxCommand {
+            argv: vec![exe.to_string_lossy().to_string(), "--landlock-exec".to_string()],
+            env: vec![(SPEC_ENV.to_string(), spec_json)],
+        })
+    }
+}
+
+/// On Ubuntu 24.04+, AppArmor restricts unprivileged user namespaces.
+/// bwrap needs a profile that grants `userns,` permission (same as Flatpak).
+/// This checks once per process whether the profile exists and installs it via pkexec if needed.
+#[cfg(target_os = "linux")]
+fn ensure_bwrap_apparmor() {
+    use std::sync::Once;
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let profile_path = "/etc/apparmor.d/bwrap";
+        let sysctl_path = "/proc/sys/kernel/apparmor_restrict_unprivileged_userns";
+
+        // Only relevant if AppArmor restricts unprivileged userns
+        let restricted = std::fs::read_to_string(sysctl_path)
+            .map(|v| v.trim() == "1")
+            .unwrap_or(false);
+        if !restricted {
+            eprintln!("[sandbox] AppArmor userns restriction not active, skipping profile setup");
+            return;
+        }
+
+        // Check if profile already exists
+        if std::path::Path::new(profile_path).exists() {
+            eprintln!("[sandbox] bwrap AppArmor profile already installed");
+            return;
+        }
+
+        eprintln!("[sandbox] AppArmor restricts userns; installing bwrap profile via pkexec...");
+
+        let profile_content = r#"abi <abi/4.0>,
+include <tunables/global>
+
+profile bwrap /usr/bin/bwrap flags=(unconfined) {
+  userns,
+}
+"#;
+
+        // Write profile and reload via pkexec (GUI sudo prompt)
+        let script = format!(
+            "echo '{}' > {} && apparmor_parser -r {}",
+            profile_content, profile_path, profile_path
+        );
+        let status = std::process::Command::new("pkexec")
+            .args(["bash", "-c", &script])
+            .status();
+
+        match status {
+            Ok(s) if s.success() => {
+                eprintln!("[sandbox] bwrap AppArmor profile installed successfully");
+            }
+            Ok(s) => {
+                eprintln!("[sandbox] pkexec exited with {}, sandbox may not work", s);
+            }
+            Err(e) => {
+                eprintln!("[sandbox] failed to run pkexec: {}, sandbox may not work", e);
+            }
+        }
+    });
+}
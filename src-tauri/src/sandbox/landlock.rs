@@ -0,0 +1,202 @@
+//! Landlock LSM confinement for kernels where bwrap can't create user
+//! namespaces. The app can't restrict a child between fork and exec through
+//! portable-pty, so it re-executes itself as `lirah --landlock-exec`, which
+//! applies the ruleset to itself and then execs the real command.
+
+use serde::{Deserialize, Serialize};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable carrying the JSON [`LandlockSpec`] to the launcher.
+/// Not argv, so passthrough values (API keys) don't show up in `ps`.
+pub const SPEC_ENV: &str = "LIRAH_LANDLOCK_SPEC";
+
+const CREATE_RULESET_VERSION: u32 = 1;
+const RULE_PATH_BENEATH: u32 = 1;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+/// Rights that make sense on a regular file rather than a directory
+const FILE_ACCESS: u64 =
+    ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE | ACCESS_FS_IOCTL_DEV;
+const READ_ACCESS: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+    handled_access_net: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// What the launcher applies before exec. Paths are computed by the app so
+/// the launcher stays dumb.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LandlockSpec {
+    /// Trees readable and executable
+    pub read: Vec<String>,
+    /// Trees with full access
+    pub write: Vec<String>,
+    /// Directories that may only be listed: ancestors of hidden paths
+    pub list_only: Vec<String>,
+    pub deny_tcp: bool,
+    pub env: Vec<(String, String)>,
+    pub argv: Vec<String>,
+}
+
+/// Landlock ABI version of the running kernel, 0 if unsupported or disabled
+pub fn abi_version() -> i64 {
+    // SAFETY: the version query takes no attribute pointer
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<RulesetAttr>(),
+            0usize,
+            CREATE_RULESET_VERSION,
+        )
+    };
+    ret.max(0)
+}
+
+/// Every filesystem right this ABI knows about
+fn handled_fs(abi: i64) -> u64 {
+    let mut access = (1 << 13) - 1; // ABI 1: EXECUTE..MAKE_SYM
+    if abi >= 2 {
+        access |= 1 << 13; // REFER
+    }
+    if abi >= 3 {
+        access |= ACCESS_FS_TRUNCATE;
+    }
+    if abi >= 5 {
+        access |= ACCESS_FS_IOCTL_DEV;
+    }
+    access
+}
+
+/// Split `root` into rules that cover everything below it except `excluded`.
+/// Landlock only allows, never denies, so directories on the way to an
+/// excluded path get `list_only` and their other children are granted one by one.
+pub fn grant_tree(root: &Path, excluded: &[PathBuf], granted: &mut Vec<String>, list_only: &mut Vec<String>) {
+    if excluded.iter().any(|e| e == root) || !root.exists() {
+        return;
+    }
+    if !excluded.iter().any(|e| e.starts_with(root)) {
+        granted.push(root.to_string_lossy().to_string());
+        return;
+    }
+    if !root.is_dir() {
+        return;
+    }
+    list_only.push(root.to_string_lossy().to_string());
+    let Ok(entries) = std::fs::read_dir(root) else { return };
+    for entry in entries.flatten() {
+        grant_tree(&entry.path(), excluded, granted, list_only);
+    }
+}
+
+fn add_rule(ruleset: i32, path: &str, access: u64, handled: u64) -> Result<(), String> {
+    let c_path = std::ffi::CString::new(path).map_err(|_| format!("Invalid path: {}", path))?;
+    // SAFETY: c_path is a valid NUL-terminated string
+    let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+        // Vanished since the app listed it; nothing to grant
+        return Ok(());
+    }
+    let is_dir = Path::new(path).is_dir();
+    let attr = PathBeneathAttr {
+        allowed_access: access & handled & if is_dir { u64::MAX } else { FILE_ACCESS },
+        parent_fd: fd,
+    };
+    // SAFETY: attr lives for the duration of the call, fd is open
+    let ret = unsafe {
+        libc::syscall(libc::SYS_landlock_add_rule, ruleset, RULE_PATH_BENEATH, &attr as *const PathBeneathAttr, 0u32)
+    };
+    let err = std::io::Error::last_os_error();
+    // SAFETY: fd was opened above and isn't used after this
+    unsafe { libc::close(fd) };
+    if ret < 0 {
+        return Err(format!("Failed to add Landlock rule for {}: {}", path, err));
+    }
+    Ok(())
+}
+
+/// Restrict the current process according to `spec`. Irreversible.
+fn restrict_self(spec: &LandlockSpec) -> Result<(), String> {
+    let abi = abi_version();
+    if abi < 1 {
+        return Err("Landlock is not supported by this kernel".to_string());
+    }
+    if spec.deny_tcp && abi < 4 {
+        return Err("Network isolation needs Landlock ABI 4 (Linux 6.7+)".to_string());
+    }
+
+    let handled = handled_fs(abi);
+    let attr = RulesetAttr {
+        handled_access_fs: handled,
+        handled_access_net: if spec.deny_tcp { ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP } else { 0 },
+    };
+    // Kernels before ABI 4 only know the first field
+    let attr_size = if abi >= 4 { std::mem::size_of::<RulesetAttr>() } else { std::mem::size_of::<u64>() };
+    // SAFETY: attr is a valid ruleset_attr of attr_size bytes
+    let ruleset = unsafe {
+        libc::syscall(libc::SYS_landlock_create_ruleset, &attr as *const RulesetAttr, attr_size, 0u32)
+    };
+    if ruleset < 0 {
+        return Err(format!("Failed to create Landlock ruleset: {}", std::io::Error::last_os_error()));
+    }
+    let ruleset = ruleset as i32;
+
+    let result = (|| {
+        for path in &spec.list_only {
+            add_rule(ruleset, path, ACCESS_FS_READ_DIR, handled)?;
+        }
+        for path in &spec.read {
+            add_rule(ruleset, path, READ_ACCESS, handled)?;
+        }
+        for path in &spec.write {
+            add_rule(ruleset, path, handled, handled)?;
+        }
+
+        // SAFETY: plain prctl/syscall with integer arguments
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(format!("Failed to set no_new_privs: {}", std::io::Error::last_os_error()));
+        }
+        if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0u32) } != 0 {
+            return Err(format!("Failed to enforce Landlock ruleset: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    })();
+
+    // SAFETY: ruleset fd is ours and no longer needed once enforced
+    unsafe { libc::close(ruleset) };
+    result
+}
+
+/// Entry point of `lirah --landlock-exec`. Only returns on failure; the PTY
+/// shows the error instead of an unconfined shell.
+pub fn run_launcher() -> Result<(), String> {
+    let raw = std::env::var(SPEC_ENV).map_err(|_| format!("{} is not set", SPEC_ENV))?;
+    let spec: LandlockSpec =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid Landlock spec: {}", e))?;
+    let program = spec.argv.first().ok_or_else(|| "Nothing to execute".to_string())?;
+
+    restrict_self(&spec)?;
+
+    let err = std::process::Command::new(program)
+        .args(&spec.argv[1..])
+        .env_clear()
+        .envs(spec.env.iter().map(|(k, v)| (k, v)))
+        .exec();
+    Err(format!("Failed to exec {}: {}", program, err))
+}
@@ -0,0 +1,364 @@
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How often a limited session's cgroup counters are polled
+const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Scope units we create are named `lirah-<id>.scope`
+const SCOPE_PREFIX: &str = "lirah-";
+
+/// systemd-run registers its scope over D-Bus before exec'ing the command, so
+/// the cgroup appears shortly after the spawn; this is how long we wait for it
+const SCOPE_WAIT: Duration = Duration::from_secs(10);
+const SCOPE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Optional per-session resource limits. Memory, CPU and process limits go
+/// through a systemd user scope (cgroup v2) and are refused without one:
+/// RLIMIT_NPROC counts every process of the user and RLIMIT_AS kills Node
+/// long before it uses that much memory. Open files is an rlimit.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub memory_mb: Option<u64>,
+    /// Share of one core, e.g. 200 for two full cores
+    pub cpu_percent: Option<u32>,
+    pub max_processes: Option<u64>,
+    pub max_open_files: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Enforcement {
+    /// systemd scope, counters and events available
+    Cgroup,
+    /// Open files rlimit only; per-process, no events
+    Rlimit,
+    #[default]
+    None,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LimitEvents {
+    pub memory_max: u64,
+    pub oom_kills: u64,
+    pub pids_max: u64,
+    pub cpu_throttled: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ResourceUsage {
+    pub enforcement: Enforcement,
+    pub memory_bytes: Option<u64>,
+    pub memory_max_bytes: Option<u64>,
+    pub cpu_usage_usec: Option<u64>,
+    pub cpu_quota_percent: Option<u32>,
+    pub processes: Option<u64>,
+    pub max_processes: Option<u64>,
+    pub open_files: Option<u64>,
+    pub max_open_files: Option<u64>,
+    pub events: LimitEvents,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.memory_mb.is_none()
+            && self.cpu_percent.is_none()
+            && self.max_processes.is_none()
+            && self.max_open_files.is_none()
+    }
+
+    fn needs_cgroup(&self) -> bool {
+        self.memory_mb.is_some() || self.cpu_percent.is_some() || self.max_processes.is_some()
+    }
+
+    /// Prefix `argv` with systemd-run and/or prlimit so the limits cover the
+    /// whole process tree (bwrap included). Refuses rather than spawning a
+    /// session without the limits the caller asked for. Also returns the
+    /// scope unit name when the limits go through a cgroup.
+    pub fn wrap_argv(&self, argv: Vec<String>) -> Result<(Vec<String>, Enforcement, Option<String>), String> {
+        if self.is_empty() {
+            return Ok((argv, Enforcement::None, None));
+        }
+
+        let mut wrapped: Vec<String> = Vec::new();
+        let mut enforcement = Enforcement::Rlimit;
+        let mut scope = None;
+
+        if self.needs_cgroup() {
+            if systemd_scope_available() {
+                enforcement = Enforcement::Cgroup;
+                let unit = format!("{}{}", SCOPE_PREFIX, uuid::Uuid::new_v4().simple());
+                wrapped.extend(["systemd-run", "--user", "--scope", "--quiet", "--collect"].map(String::from));
+                wrapped.push(format!("--unit={}", unit));
+                scope = Some(format!("{}.scope", unit));
+                if let Some(mb) = self.memory_mb {
+                    wrapped.push(format!("--property=MemoryMax={}M", mb));
+                    // Fail fast instead of thrashing swap
+                    wrapped.push("--property=MemorySwapMax=0".to_string());
+                }
+                if let Some(pct) = self.cpu_percent {
+                    wrapped.push(format!("--property=CPUQuota={}%", pct));
+                }
+                if let Some(n) = self.max_processes {
+                    wrapped.push(format!("--property=TasksMax={}", n));
+                }
+                wrapped.push("--".to_string());
+            } else {
+                return Err(
+                    "Memory, CPU and process limits need a systemd user session with cgroup v2".to_string(),
+                );
+            }
+        }
+
+        let mut rlimits: Vec<String> = Vec::new();
+        if let Some(n) = self.max_open_files {
+            rlimits.push(format!("--nofile={}", n));
+        }
+        if !rlimits.is_empty() {
+            if find_in_path("prlimit").is_none() {
+                return Err("prlimit (util-linux) is required for rlimit-based limits".to_string());
+            }
+            wrapped.push("prlimit".to_string());
+            wrapped.extend(rlimits);
+            wrapped.push("--".to_string());
+        }
+
+        wrapped.extend(argv);
+        Ok((wrapped, enforcement, scope))
+    }
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|p| p.is_file())
+    })
+}
+
+/// Whether `systemd-run --user --scope` works here (needs a user manager)
+fn systemd_scope_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Path::new("/sys/fs/cgroup/cgroup.controllers").exists()
+            && std::process::Command::new("systemd-run")
+                .args(["--user", "--scope", "--quiet", "--collect", "--", "true"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+    })
+}
+
+/// The cgroup v2 directory of a process, if it sits in one of our scopes
+fn session_cgroup(pid: u32) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let rel = content.lines().find_map(|l| l.strip_prefix("0::"))?;
+    let dir = Path::new("/sys/fs/cgroup").join(rel.trim_start_matches('/'));
+    let in_scope = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with(SCOPE_PREFIX));
+    in_scope.then_some(dir)
+}
+
+fn current_uid() -> Option<u32> {
+    let content = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = content.lines().find(|l| l.starts_with("Uid:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// The cgroup v2 directory of one of our scope units, if systemd has created
+/// it yet. `systemd-run --user --scope` puts scopes under the user manager's
+/// app.slice (older systemd: directly under the manager).
+fn scope_cgroup(scope: &str) -> Option<PathBuf> {
+    let uid = current_uid()?;
+    let manager = PathBuf::from(format!(
+        "/sys/fs/cgroup/user.slice/user-{uid}.slice/user@{uid}.service"
+    ));
+    [manager.join("app.slice").join(scope), manager.join(scope)]
+        .into_iter()
+        .find(|dir| dir.is_dir())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Parse a flat-keyed file like `memory.events` or `cpu.stat`
+fn read_keyed(path: &Path) -> HashMap<String, u64> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| {
+            let (key, val) = l.split_once(' ')?;
+            Some((key.to_string(), val.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn read_events(cgroup: &Path) -> LimitEvents {
+    let memory = read_keyed(&cgroup.join("memory.events"));
+    let pids = read_keyed(&cgroup.join("pids.events"));
+    let cpu = read_keyed(&cgroup.join("cpu.stat"));
+    LimitEvents {
+        memory_max: memory.get("max").copied().unwrap_or(0),
+        oom_kills: memory.get("oom_kill").copied().unwrap_or(0),
+        pids_max: pids.get("max").copied().unwrap_or(0),
+        cpu_throttled: cpu.get("nr_throttled").copied().unwrap_or(0),
+    }
+}
+
+fn count_fds(pid: u32) -> u64 {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|d| d.count() as u64)
+        .unwrap_or(0)
+}
+
+/// Soft limit from `/proc/<pid>/limits`, e.g. "Max open files"
+fn read_rlimit(pid: u32, name: &str) -> Option<u64> {
+    let content = std::fs::read_to_string(format!("/proc/{}/limits", pid)).ok()?;
+    let line = content.lines().find(|l| l.starts_with(name))?;
+    line[name.len()..].split_whitespace().next()?.parse().ok()
+}
+
+/// `/proc/<pid>/status` value in kB, e.g. "VmRSS"
+fn read_status_kb(pid: u32, key: &str) -> Option<u64> {
+    let content = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = content.lines().find(|l| l.starts_with(key))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn usage_for_pid(pid: u32) -> ResourceUsage {
+    let max_open_files = read_rlimit(pid, "Max open files");
+
+    if let Some(cgroup) = session_cgroup(pid) {
+        let procs: Vec<u32> = std::fs::read_to_string(cgroup.join("cgroup.procs"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| l.trim().parse().ok())
+            .collect();
+        // cpu.max is "<quota> <period>" or "max <period>"
+        let cpu_quota_percent = std::fs::read_to_string(cgroup.join("cpu.max"))
+            .ok()
+            .and_then(|s| {
+                let mut parts = s.split_whitespace();
+                let quota: u64 = parts.next()?.parse().ok()?;
+                let period: u64 = parts.next()?.parse().ok()?;
+                Some((quota * 100 / period.max(1)) as u32)
+            });
+
+        return ResourceUsage {
+            enforcement: Enforcement::Cgroup,
+            memory_bytes: read_u64(&cgroup.join("memory.current")),
+            memory_max_bytes: read_u64(&cgroup.join("memory.max")),
+            cpu_usage_usec: read_keyed(&cgroup.join("cpu.stat")).get("usage_usec").copied(),
+            cpu_quota_percent,
+            processes: read_u64(&cgroup.join("pids.current")),
+            max_processes: read_u64(&cgroup.join("pids.max")),
+            open_files: Some(procs.iter().map(|p| count_fds(*p)).sum()),
+            max_open_files,
+            events: read_events(&cgroup),
+        };
+    }
+
+    // Without a scope only open files can be limited; count it as enforced
+    // when the session runs with less than the app itself
+    let own_open_files = read_rlimit(std::process::id(), "Max open files");
+    let lowered = matches!((max_open_files, own_open_files), (Some(n), Some(own)) if n < own);
+    ResourceUsage {
+        enforcement: if lowered { Enforcement::Rlimit } else { Enforcement::None },
+        memory_bytes: read_status_kb(pid, "VmRSS").map(|kb| kb * 1024),
+        memory_max_bytes: None,
+        cpu_usage_usec: None,
+        cpu_quota_percent: None,
+        processes: None,
+        max_processes: None,
+        open_files: Some(count_fds(pid)),
+        max_open_files,
+        events: LimitEvents::default(),
+    }
+}
+
+/// Report a terminal's resource usage against its limits
+#[tauri::command]
+pub fn get_terminal_resource_usage(
+    session_id: String,
+    state: tauri::State<AppState>,
+) -> Result<ResourceUsage, String> {
+    let pid = {
+        let state_lock = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        if let Some(session) = state_lock.pty_sessions.get(&session_id) {
+            session.child.process_id()
+        } else if let Some(attachment) = state_lock.daemon_sessions.get(&session_id) {
+            attachment.pid
+        } else {
+            return Err(format!("Session not found: {}", session_id));
+        }
+    }
+    .ok_or_else(|| "Could not get process ID".to_string())?;
+
+    Ok(usage_for_pid(pid))
+}
+
+/// Watch a limited session's cgroup and emit `terminal-limit-hit` whenever a
+/// limit starts being hit. Edge-triggered so a throttled build doesn't spam.
+pub fn spawn_monitor(app: AppHandle, session_id: String, scope: String, shutdown: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let deadline = std::time::Instant::now() + SCOPE_WAIT;
+        let cgroup = loop {
+            if shutdown.load(Ordering::SeqCst) {
+                return;
+            }
+            if let Some(dir) = scope_cgroup(&scope) {
+                break dir;
+            }
+            if std::time::Instant::now() >= deadline {
+                eprintln!(
+                    "[sandbox] Cgroup for {} of session {} never appeared; not watching its limits",
+                    scope, session_id
+                );
+                return;
+            }
+            std::thread::sleep(SCOPE_POLL_INTERVAL);
+        };
+
+        let mut last = read_events(&cgroup);
+        let mut hitting: HashMap<&'static str, bool> = HashMap::new();
+
+        while !shutdown.load(Ordering::SeqCst) && cgroup.exists() {
+            std::thread::sleep(MONITOR_INTERVAL);
+            let now = read_events(&cgroup);
+            let deltas = [
+                ("memory", now.memory_max.saturating_sub(last.memory_max)),
+                ("oom_kill", now.oom_kills.saturating_sub(last.oom_kills)),
+                ("processes", now.pids_max.saturating_sub(last.pids_max)),
+                ("cpu", now.cpu_throttled.saturating_sub(last.cpu_throttled)),
+            ];
+            for (limit, delta) in deltas {
+                let was_hitting = hitting.insert(limit, delta > 0).unwrap_or(false);
+                if delta > 0 && !was_hitting {
+                    eprintln!("[sandbox] Session {} hit its {} limit", session_id, limit);
+                    let _ = app.emit(
+                        "terminal-limit-hit",
+                        serde_json::json!({
+                            "session_id": session_id,
+                            "limit": limit,
+                            "count": delta,
+                        }),
+                    );
+                }
+            }
+            last = now;
+        }
+    });
+}
@@ -0,0 +1,28 @@
+//! Sandbox configuration for terminals spawned with `sandbox: true`.
+//!
+//! - `policy`: TOML policies (global + per-project) resolved into bwrap arguments
+//! - `backend`: the `SandboxBackend` trait with bwrap and Landlock implementations
+//! - `landlock`: Landlock ruleset and the `--landlock-exec` launcher
+//! - `limits`: per-session memory/CPU/process/open-file limits and usage
+//! - `overlay`: copy-on-write project mounts and review of their changes
+//! - `secrets`: per-project secret globs masked at spawn, with an audit log
+//! - `types`: per-spawn sandbox options
+
+pub mod backend;
+#[cfg(target_os = "linux")]
+pub mod landlock;
+pub mod limits;
+pub mod overlay;
+pub mod policy;
+pub mod secrets;
+pub mod types;
+
+pub use backend::{get_sandbox_backends, select_backend, SandboxBackendKind, SandboxRequest};
+pub use limits::get_terminal_resource_usage;
+pub use overlay::{
+    apply_overlay_changes, delete_overlay, diff_overlay_change, discard_overlay_changes,
+    list_overlay_changes, list_overlays,
+};
+pub use policy::{get_sandbox_policy, NetworkMode, SandboxPolicy};
+pub use secrets::get_masked_secrets;
+pub use types::SandboxOptions;
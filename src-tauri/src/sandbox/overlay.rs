@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Metadata stored next to each overlay's upper/work dirs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OverlayInfo {
+    pub id: String,
+    pub project_dir: String,
+    pub created_at: u64,
+    /// PID of the sandbox process using the overlay, once spawned
+    pub pid: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OverlayChange {
+    /// Path relative to the project root
+    pub path: String,
+    pub kind: ChangeKind,
+    pub is_dir: bool,
+}
+
+/// Under the app state dir, which sandboxes can't see, so a sandbox can't
+/// rewrite where its changes get applied or clear the pid that marks it running
+fn overlays_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("overlays")
+}
+
+fn overlay_root(id: &str) -> Result<PathBuf, String> {
+    // Ids are uuids; anything else could point outside the overlays dir
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        return Err(format!("Invalid overlay id: {}", id));
+    }
+    Ok(overlays_dir().join(id))
+}
+
+pub fn upper_dir(id: &str) -> Result<PathBuf, String> {
+    Ok(overlay_root(id)?.join("upper"))
+}
+
+pub fn work_dir(id: &str) -> Result<PathBuf, String> {
+    Ok(overlay_root(id)?.join("work"))
+}
+
+/// Create a fresh, empty overlay for a project
+pub fn create_overlay(project_dir: &str) -> Result<OverlayInfo, String> {
+    let info = OverlayInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_dir: project_dir.to_string(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        pid: None,
+    };
+    fs::create_dir_all(upper_dir(&info.id)?)
+        .map_err(|e| format!("Failed to create overlay upper dir: {}", e))?;
+    fs::create_dir_all(work_dir(&info.id)?)
+        .map_err(|e| format!("Failed to create overlay work dir: {}", e))?;
+    write_info(&info)?;
+    Ok(info)
+}
+
+pub fn write_info(info: &OverlayInfo) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(info).map_err(|e| e.to_string())?;
+    fs::write(overlay_root(&info.id)?.join("overlay.json"), json)
+        .map_err(|e| format!("Failed to write overlay metadata: {}", e))
+}
+
+fn read_info(id: &str) -> Result<OverlayInfo, String> {
+    let path = overlay_root(id)?.join("overlay.json");
+    let content = fs::read_to_string(&path).map_err(|_| format!("Overlay not found: {}", id))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid overlay metadata: {}", e))
+}
+
+/// `--overlay` needs bwrap 0.8 or newer
+#[cfg(target_os = "linux")]
+pub fn bwrap_supports_overlay() -> bool {
+    let Ok(output) = std::process::Command::new("/usr/bin/bwrap").arg("--version").output() else {
+        return false;
+    };
+    // "bubblewrap 0.9.0"
+    let version = String::from_utf8_lossy(&output.stdout);
+    let mut parts = version
+        .split_whitespace()
+        .last()
+        .unwrap_or("")
+        .split('.')
+        .map(|p| p.parse::<u32>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    major > 0 || minor >= 8
+}
+
+/// Whether the sandbox that mounted this overlay is still running.
+/// The upper dir must not be touched while the overlay is mounted.
+fn is_in_use(info: &OverlayInfo) -> bool {
+    info.pid
+        .map(|pid| Path::new(&format!("/proc/{}", pid)).exists())
+        .unwrap_or(false)
+}
+
+/// overlayfs records deletions as 0/0 character devices in the upper dir
+#[cfg(unix)]
+fn is_whiteout(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+    meta.file_type().is_char_device() && meta.rdev() == 0
+}
+
+#[cfg(not(unix))]
+fn is_whiteout(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+/// Whether an upper dir replaced its lower counterpart wholesale (deleted and
+/// recreated). Unprivileged bwrap mounts with `userxattr`, so the marker is
+/// `user.overlay.opaque`; a privileged mount uses `trusted.overlay.opaque`.
+#[cfg(target_os = "linux")]
+fn is_opaque(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(c_path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    [&b"user.overlay.opaque\0"[..], &b"trusted.overlay.opaque\0"[..]]
+        .iter()
+        .any(|name| {
+            let mut value = [0u8; 1];
+            // SAFETY: both names are NUL-terminated and value is valid for its length
+            let len = unsafe {
+                libc::lgetxattr(
+                    c_path.as_ptr(),
+                    name.as_ptr() as *const libc::c_char,
+                    value.as_mut_ptr() as *mut libc::c_void,
+                    value.len(),
+                )
+            };
+            len == 1 && value[0] == b'y'
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_opaque(_dir: &Path) -> bool {
+    false
+}
+
+/// A lower entry hidden by an opaque parent dir rather than by a whiteout
+fn deleted_by_opaque_parent(upper: &Path, lower: &Path) -> bool {
+    upper.symlink_metadata().is_err()
+        && lower.symlink_metadata().is_ok()
+        && upper.parent().is_some_and(is_opaque)
+}
+
+/// Walk the upper dir and classify every entry against the real tree.
+/// Children of an opaque dir that only exist in the real tree are reported
+/// as deleted, since the sandbox no longer sees them.
+fn collect_changes(upper: &Path, lower: &Path) -> Vec<OverlayChange> {
+    let mut changes = Vec::new();
+    let mut stack = vec![PathBuf::new()];
+
+    while let Some(rel_dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(upper.join(&rel_dir)) else { continue };
+        for entry in entries.flatten() {
+            let rel = rel_dir.join(entry.file_name());
+            let Ok(meta) = entry.path().symlink_metadata() else { continue };
+            let lower_path = lower.join(&rel);
+            let in_lower = lower_path.symlink_metadata().is_ok();
+            let rel_str = rel.to_string_lossy().to_string();
+
+            if is_whiteout(&meta) {
+                changes.push(OverlayChange {
+                    path: rel_str,
+                    kind: ChangeKind::Deleted,
+                    is_dir: lower_path.is_dir(),
+                });
+            } else if meta.is_dir() {
+                // Existing dirs are copied up just to hold changed children
+                if !in_lower {
+                    changes.push(OverlayChange {
+                        path: rel_str,
+                        kind: ChangeKind::Added,
+                        is_dir: true,
+                    });
+                } else if is_opaque(&entry.path()) {
+                    for lower_entry in fs::read_dir(&lower_path).into_iter().flatten().flatten() {
+                        let name = lower_entry.file_name();
+                        if entry.path().join(&name).symlink_metadata().is_err() {
+                            changes.push(OverlayChange {
+                                path: rel.join(&name).to_string_lossy().to_string(),
+                                kind: ChangeKind::Deleted,
+                                is_dir: lower_entry.path().is_dir(),
+                            });
+                        }
+                    }
+                }
+                stack.push(rel);
+            } else {
+                changes.push(OverlayChange {
+                    path: rel_str,
+                    kind: if in_lower { ChangeKind::Modified } else { ChangeKind::Added },
+                    is_dir: false,
+                });
+            }
+        }
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Reject absolute paths and `..` so requests stay inside the overlay
+fn checked_relative(path: &str) -> Result<&Path, String> {
+    let p = Path::new(path);
+    if p.is_absolute() || p.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("Invalid path: {}", path));
+    }
+    Ok(p)
+}
+
+fn copy_entry(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let meta = from.symlink_metadata().map_err(|e| e.to_string())?;
+
+    #[cfg(unix)]
+    if meta.file_type().is_symlink() {
+        let target = fs::read_link(from).map_err(|e| e.to_string())?;
+        let _ = fs::remove_file(to);
+        return std::os::unix::fs::symlink(target, to)
+            .map_err(|e| format!("Failed to create symlink {}: {}", to.display(), e));
+    }
+
+    if meta.is_dir() {
+        fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        for entry in fs::read_dir(from).map_err(|e| e.to_string())?.flatten() {
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to copy {}: {}", to.display(), e))
+}
+
+fn remove_entry(path: &Path) -> Result<(), String> {
+    let Ok(meta) = path.symlink_metadata() else { return Ok(()) };
+    if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+fn idle_overlay(overlay_id: &str) -> Result<OverlayInfo, String> {
+    let info = read_info(overlay_id)?;
+    if is_in_use(&info) {
+        return Err("The sandbox using this overlay is still running; close it first".to_string());
+    }
+    Ok(info)
+}
+
+/// List overlays on disk, newest first
+#[tauri::command]
+pub fn list_overlays(project_dir: Option<String>) -> Result<Vec<serde_json::Value>, String> {
+    let mut overlays = Vec::new();
+    if let Ok(entries) = fs::read_dir(overlays_dir()) {
+        for entry in entries.flatten() {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let Ok(info) = read_info(&id) else { continue };
+            if project_dir.as_ref().is_some_and(|p| p != &info.project_dir) {
+                continue;
+            }
+            overlays.push(serde_json::json!({
+                "id": info.id,
+                "project_dir": info.project_dir,
+                "created_at": info.created_at,
+                "in_use": is_in_use(&info),
+            }));
+        }
+    }
+    overlays.sort_by_key(|o| std::cmp::Reverse(o["created_at"].as_u64().unwrap_or(0)));
+    Ok(overlays)
+}
+
+/// Files an overlay sandbox has added, modified or deleted
+#[tauri::command]
+pub fn list_overlay_changes(overlay_id: String) -> Result<Vec<OverlayChange>, String> {
+    let info = read_info(&overlay_id)?;
+    Ok(collect_changes(&upper_dir(&overlay_id)?, Path::new(&info.project_dir)))
+}
+
+/// Unified diff of one changed path against the real tree
+#[tauri::command]
+pub fn diff_overlay_change(overlay_id: String, path: String) -> Result<String, String> {
+    let info = read_info(&overlay_id)?;
+    let rel = checked_relative(&path)?;
+    let upper = upper_dir(&overlay_id)?.join(rel);
+    let lower = Path::new(&info.project_dir).join(rel);
+
+    let upper_meta = upper.symlink_metadata().ok();
+    let new_side = match &upper_meta {
+        Some(meta) if !is_whiteout(meta) => upper.clone(),
+        _ => PathBuf::from("/dev/null"),
+    };
+    let old_side = if lower.exists() { lower } else { PathBuf::from("/dev/null") };
+
+    // git diff --no-index exits with 1 when the files differ
+    let output = std::process::Command::new("git")
+        .args(["diff", "--no-index", "--no-color", "--"])
+        .arg(&old_side)
+        .arg(&new_side)
+        .output()
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    match output.status.code() {
+        Some(0) | Some(1) => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        _ => Err(String::from_utf8_lossy(&output.stderr).to_string()),
+    }
+}
+
+/// Write the selected changes through to the real project tree. `project_dir`
+/// is the project the caller means to write to; the overlay must have been
+/// created for it.
+#[tauri::command]
+pub fn apply_overlay_changes(
+    overlay_id: String,
+    project_dir: String,
+    paths: Vec<String>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let info = idle_overlay(&overlay_id)?;
+    if info.project_dir != project_dir {
+        return Err(format!(
+            "Overlay {} belongs to {}, not {}",
+            overlay_id, info.project_dir, project_dir
+        ));
+    }
+    let upper_root = upper_dir(&overlay_id)?;
+    let lower_root = PathBuf::from(&info.project_dir);
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let result = checked_relative(&path).and_then(|rel| {
+                let upper = upper_root.join(rel);
+                let lower = lower_root.join(rel);
+                if deleted_by_opaque_parent(&upper, &lower) {
+                    return remove_entry(&lower);
+                }
+                let meta = upper
+                    .symlink_metadata()
+                    .map_err(|_| format!("No change recorded for {}", path))?;
+                if is_whiteout(&meta) {
+                    remove_entry(&lower)?;
+                } else {
+                    if lower.symlink_metadata().is_ok() && (meta.is_dir() != lower.is_dir()) {
+                        remove_entry(&lower)?;
+                    }
+                    copy_entry(&upper, &lower)?;
+                }
+                // Applied changes are no longer pending
+                remove_entry(&upper)
+            });
+            serde_json::json!({ "path": path, "ok": result.is_ok(), "error": result.err() })
+        })
+        .collect())
+}
+
+/// Drop the selected changes from the overlay, leaving the real tree untouched
+#[tauri::command]
+pub fn discard_overlay_changes(overlay_id: String, paths: Vec<String>) -> Result<Vec<serde_json::Value>, String> {
+    let info = idle_overlay(&overlay_id)?;
+    let upper_root = upper_dir(&overlay_id)?;
+    let lower_root = PathBuf::from(&info.project_dir);
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let result = checked_relative(&path).and_then(|rel| {
+                let upper = upper_root.join(rel);
+                let lower = lower_root.join(rel);
+                // Bring the hidden entry back into the opaque dir
+                if deleted_by_opaque_parent(&upper, &lower) {
+                    return copy_entry(&lower, &upper);
+                }
+                remove_entry(&upper)
+            });
+            serde_json::json!({ "path": path, "ok": result.is_ok(), "error": result.err() })
+        })
+        .collect())
+}
+
+/// Delete an overlay and everything still pending in it
+#[tauri::command]
+pub fn delete_overlay(overlay_id: String) -> Result<(), String> {
+    idle_overlay(&overlay_id)?;
+    let root = overlay_root(&overlay_id)?;
+    // overlayfs leaves work/work behind with mode 000; make it removable first
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(entries) = fs::read_dir(root.join("work")) {
+            for entry in entries.flatten() {
+                let _ = fs::set_permissions(entry.path(), fs::Permissions::from_mode(0o700));
+            }
+        }
+    }
+    fs::remove_dir_all(&root).map_err(|e| format!("Failed to delete overlay: {}", e))
+}
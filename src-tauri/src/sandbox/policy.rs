@@ -0,0 +1,363 @@
+use crate::sandbox::secrets::{self, DEFAULT_SECRET_GLOBS};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Home-relative paths kept read-only inside the sandbox unless the global
+/// policy sets `inherit_defaults = false`
+const DEFAULT_READ_ONLY: &[&str] = &[
+    "~/.ssh",
+    "~/.gnupg",
+    "~/.bashrc",
+    "~/.bash_profile",
+    "~/.profile",
+    "~/.zshrc",
+    "~/.zprofile",
+    "~/.pam_environment",
+    "~/.gitconfig",
+    "~/.config/git",
+    "~/.config/autostart",
+    "~/.config/systemd/user",
+    "~/.config/environment.d",
+    "~/.local/bin",
+    // Shell configs
+    "~/.config/fish",
+    "~/.config/nushell",
+    // Desktop environment startup scripts
+    "~/.config/plasma-workspace/env",
+    "~/.config/hypr",
+    "~/.xinitrc",
+    "~/.xprofile",
+    // File handler and .desktop registration
+    "~/.config/mimeapps.list",
+    "~/.local/share/applications",
+    // Sandbox policies themselves must not be editable from inside
+    "~/.config/lirah",
+];
+
+/// Environment variables re-exported into the sandbox by default
+const DEFAULT_ENV_PASSTHROUGH: &[&str] = &[
+    "HOME", "USER", "LOGNAME", "PATH", "SHELL", "TERM",
+    "LANG", "LC_ALL", "XDG_RUNTIME_DIR", "DBUS_SESSION_BUS_ADDRESS",
+    "DISPLAY", "WAYLAND_DISPLAY",
+    // Claude Code / Node.js needs
+    "NODE_HOME", "NVM_DIR", "npm_config_prefix",
+    // API keys Claude Code needs to function
+    "ANTHROPIC_API_KEY", "CLAUDE_API_KEY",
+];
+
+/// Project policy location, relative to the project root
+pub const PROJECT_POLICY_PATH: &str = ".lirah/sandbox.toml";
+
+/// Directory holding the project policy. Read-only inside the sandbox even
+/// when it doesn't exist yet, so a policy can't be planted for the next spawn.
+pub const PROJECT_STATE_DIR: &str = ".lirah";
+
+/// Home-relative directory with the app's own state: the session daemon
+/// socket, ssh control sockets and files the app acts on unconfined. Hidden
+/// from every sandbox, whatever the policy says.
+const APP_STATE_DIR: &str = ".lirah";
+
+/// Parts of the app state sandboxed terminals still read: the shell hooks
+/// they source and pastes spilled to a file
+const SANDBOX_VISIBLE_STATE: &[&str] = &["shell-integration", "pastes"];
+
+/// The app state directory, and the subdirectories re-exposed read-only
+/// inside it (created if missing so they can be bound)
+pub fn app_state_paths() -> Result<(PathBuf, Vec<PathBuf>), String> {
+    let dir = dirs::home_dir()
+        .ok_or("Could not determine home directory")?
+        .join(APP_STATE_DIR);
+    let mut visible = Vec::new();
+    for name in SANDBOX_VISIBLE_STATE {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        visible.push(path);
+    }
+    Ok((dir, visible))
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkMode {
+    #[default]
+    Full,
+    None,
+}
+
+/// On-disk policy format, shared by the global and the project file
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    read_only: Vec<String>,
+    hidden: Vec<String>,
+    writable: Vec<String>,
+    env_passthrough: Vec<String>,
+    secrets: Vec<String>,
+    network: Option<NetworkMode>,
+    inherit_defaults: Option<bool>,
+}
+
+/// Fully resolved sandbox policy for one project: built-in defaults, then the
+/// global file, then the project file. Paths are absolute.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SandboxPolicy {
+    pub read_only: Vec<String>,
+    pub hidden: Vec<String>,
+    pub writable: Vec<String>,
+    pub env_passthrough: Vec<String>,
+    /// Globs for secret files inside the project, masked at spawn time
+    pub secrets: Vec<String>,
+    /// Project paths matching `secrets` when the policy was resolved
+    pub masked_secrets: Vec<String>,
+    pub network: NetworkMode,
+    /// Policy files that contributed, in merge order
+    pub sources: Vec<String>,
+}
+
+pub fn global_policy_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".config")
+        .join("lirah")
+        .join("sandbox.toml")
+}
+
+impl SandboxPolicy {
+    /// Load and merge the policies that apply to `project_dir`
+    pub fn resolve(project_dir: Option<&str>) -> Result<Self, String> {
+        let home = dirs::home_dir().ok_or_else(|| "Could not determine home directory".to_string())?;
+        let project = project_dir.map(PathBuf::from).filter(|p| p.is_dir());
+
+        let global_path = global_policy_path();
+        let global = read_policy_file(&global_path)?;
+
+        let mut policy = SandboxPolicy::default();
+        if global.as_ref().and_then(|g| g.inherit_defaults).unwrap_or(true) {
+            for path in DEFAULT_READ_ONLY {
+                policy.read_only.push(expand_path(path, &home, None)?);
+            }
+            policy.env_passthrough = DEFAULT_ENV_PASSTHROUGH.iter().map(|v| v.to_string()).collect();
+            policy.secrets = DEFAULT_SECRET_GLOBS.iter().map(|g| g.to_string()).collect();
+        }
+
+        if let Some(global) = global {
+            policy.merge(global, &home, None)?;
+            policy.sources.push(global_path.to_string_lossy().to_string());
+        }
+
+        if let Some(project) = &project {
+            let project_path = project.join(PROJECT_POLICY_PATH);
+            if let Some(file) = read_policy_file(&project_path)? {
+                if file.inherit_defaults.is_some() {
+                    return Err(format!(
+                        "{}: inherit_defaults is only allowed in the global policy",
+                        project_path.display()
+                    ));
+                }
+                // A project can't widen access outside of its own tree
+                let outside: Vec<&String> = file
+                    .writable
+                    .iter()
+                    .filter(|p| {
+                        expand_path(p, &home, Some(project))
+                            .map(|abs| !Path::new(&abs).starts_with(project))
+                            .unwrap_or(true)
+                    })
+                    .collect();
+                if !outside.is_empty() {
+                    return Err(format!(
+                        "{}: writable paths must stay inside the project: {:?}",
+                        project_path.display(),
+                        outside
+                    ));
+                }
+                policy.merge(file, &home, Some(project))?;
+                policy.sources.push(project_path.to_string_lossy().to_string());
+            }
+        }
+
+        for list in [&mut policy.read_only, &mut policy.hidden, &mut policy.writable, &mut policy.secrets] {
+            let mut seen = std::collections::HashSet::new();
+            list.retain(|p| seen.insert(p.clone()));
+        }
+        if let Some(project) = &project {
+            policy.masked_secrets = secrets::find_secrets(project, &policy.secrets)?;
+        }
+        policy.env_passthrough.sort();
+        policy.env_passthrough.dedup();
+        Ok(policy)
+    }
+
+    fn merge(&mut self, file: PolicyFile, home: &Path, base: Option<&Path>) -> Result<(), String> {
+        for path in &file.read_only {
+            self.read_only.push(expand_path(path, home, base)?);
+        }
+        for path in &file.hidden {
+            self.hidden.push(expand_path(path, home, base)?);
+        }
+        for path in &file.writable {
+            let abs = expand_path(path, home, base)?;
+            if abs == "/" || Path::new(&abs) == home {
+                return Err(format!("Refusing to bind {} writable", abs));
+            }
+            self.writable.push(abs);
+        }
+        for var in file.env_passthrough {
+            if var.is_empty() || var.contains('=') {
+                return Err(format!("Invalid environment variable name: {:?}", var));
+            }
+            self.env_passthrough.push(var);
+        }
+        secrets::compile_globs(&file.secrets)?;
+        self.secrets.extend(file.secrets);
+        // Layers may only tighten network access
+        if file.network == Some(NetworkMode::None) {
+            self.network = NetworkMode::None;
+        }
+        Ok(())
+    }
+
+    /// Translate the policy into bwrap arguments (everything before `--`).
+    /// Paths that don't exist on this machine are skipped. With `overlay`
+    /// (upper, work) the project is mounted copy-on-write instead of bound.
+    /// Fails if the app state dirs can't be set up, rather than leave them reachable.
+    pub fn bwrap_args(&self, home: Option<&str>, project_dir: Option<&str>, overlay: Option<(&str, &str)>) -> Result<Vec<String>, String> {
+        let mut args: Vec<String> = Vec::new();
+        let mut push = |items: &[&str]| args.extend(items.iter().map(|s| s.to_string()));
+
+        // Clear all inherited env vars to prevent leaking secrets
+        push(&["--clearenv"]);
+        push(&["--ro-bind", "/", "/", "--dev", "/dev", "--tmpfs", "/tmp"]);
+
+        // Home directory: writable by default, with sensitive paths read-only
+        if let Some(home) = home {
+            push(&["--bind", home, home]);
+        }
+        for path in self.read_only.iter().filter(|p| Path::new(p).exists()) {
+            push(&["--ro-bind", path, path]);
+        }
+        for path in self.writable.iter().filter(|p| Path::new(p).exists()) {
+            push(&["--bind", path, path]);
+        }
+
+        // App state is hidden even without the home bind, since a read-only
+        // mount doesn't stop connect() on the daemon socket. A project inside
+        // it (a workspace) is bound on top; one containing it masks it again.
+        let (state_dir, visible_state) = app_state_paths()?;
+        let state_path = state_dir.to_string_lossy().to_string();
+        let visible: Vec<String> = visible_state.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let mut state_mask = vec!["--tmpfs", state_path.as_str()];
+        for path in &visible {
+            state_mask.extend(["--ro-bind", path, path]);
+        }
+        push(&state_mask);
+
+        // Writable: project directory (may be outside home)
+        if let Some(proj) = project_dir.filter(|p| Path::new(p).is_dir()) {
+            match overlay {
+                Some((upper, work)) => push(&["--overlay-src", proj, "--overlay", upper, work, proj]),
+                None => push(&["--bind", proj, proj]),
+            }
+            // ...except where its own sandbox policy lives. Changes in an
+            // overlay only reach the project through a reviewed apply.
+            let state = Path::new(proj).join(PROJECT_STATE_DIR);
+            let state_path = state.to_string_lossy();
+            if state.exists() {
+                push(&["--ro-bind", &state_path, &state_path]);
+            } else if overlay.is_none() {
+                push(&["--tmpfs", &state_path, "--remount-ro", &state_path]);
+            }
+            if state_dir.starts_with(proj) {
+                push(&state_mask);
+            }
+        }
+
+        // Hidden paths and project secrets come last so they mask any bind above them
+        for path in self.hidden.iter().chain(&self.masked_secrets) {
+            let p = Path::new(path);
+            if p.is_dir() {
+                push(&["--tmpfs", path]);
+            } else if p.exists() {
+                push(&["--ro-bind", "/dev/null", path]);
+            }
+        }
+
+        if self.network == NetworkMode::None {
+            push(&["--unshare-net"]);
+        }
+        push(&[
+            "--unshare-uts",
+            "--unshare-ipc",
+            "--unshare-pid",
+            "--unshare-cgroup",
+            "--new-session",
+            "--die-with-parent",
+            // Mount /proc after --unshare-pid so it's scoped to sandbox PIDs
+            "--proc", "/proc",
+        ]);
+
+        // Re-export only the allowed env vars
+        for var in &self.env_passthrough {
+            if let Ok(val) = std::env::var(var) {
+                push(&["--setenv", var, &val]);
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+fn read_policy_file(path: &Path) -> Result<Option<PolicyFile>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Invalid sandbox policy {}: {}", path.display(), e))
+}
+
+/// Expand `~/` and resolve relative paths against `base` (the project root for
+/// project policies). Rejects `..` so a policy can't escape where it points.
+fn expand_path(raw: &str, home: &Path, base: Option<&Path>) -> Result<String, String> {
+    let path = if raw == "~" {
+        home.to_path_buf()
+    } else if let Some(rest) = raw.strip_prefix("~/") {
+        home.join(rest)
+    } else if Path::new(raw).is_absolute() {
+        PathBuf::from(raw)
+    } else if let Some(base) = base {
+        base.join(raw)
+    } else {
+        return Err(format!("Path must be absolute or start with ~/: {}", raw));
+    };
+
+    if path.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(format!("Path must not contain '..': {}", raw));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Show the policy that would apply to a sandboxed terminal in `project_dir`,
+/// along with the bwrap arguments it turns into.
+#[tauri::command]
+pub fn get_sandbox_policy(project_dir: Option<String>) -> Result<serde_json::Value, String> {
+    let policy = SandboxPolicy::resolve(project_dir.as_deref())?;
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
+    let mut bwrap_args = policy.bwrap_args(home.as_deref(), project_dir.as_deref(), None)?;
+
+    // Never ship passthrough values (API keys) to the UI
+    for i in 0..bwrap_args.len().saturating_sub(2) {
+        if bwrap_args[i] == "--setenv" {
+            bwrap_args[i + 2] = "<redacted>".to_string();
+        }
+    }
+
+    Ok(serde_json::json!({
+        "policy": policy,
+        "bwrap_args": bwrap_args,
+        "global_path": global_policy_path(),
+    }))
+}
@@ -0,0 +1,190 @@
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Secret globs masked in every project unless the global policy sets
+/// `inherit_defaults = false`
+pub const DEFAULT_SECRET_GLOBS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "secrets/*.json",
+];
+
+/// Don't let a huge monorepo stall terminal startup. Past this the spawn is
+/// refused, since an unscanned part could hold secrets.
+const MAX_WALK_ENTRIES: usize = 1_000_000;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// One spawn's masking record in `~/.lirah/audit/masked-secrets.jsonl`, which
+/// like the rest of the app state is hidden from sandboxes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaskAuditEntry {
+    pub session_id: String,
+    pub project_dir: Option<String>,
+    pub sandboxed: bool,
+    pub masked: Vec<String>,
+    pub timestamp: u64,
+}
+
+pub fn compile_globs(globs: &[String]) -> Result<Vec<Pattern>, String> {
+    globs
+        .iter()
+        .map(|g| Pattern::new(g.trim_start_matches('/')).map_err(|e| format!("Invalid secret glob {:?}: {}", g, e)))
+        .collect()
+}
+
+/// Gitignore-style match: globs without a `/` match the name at any depth,
+/// globs with one match the path relative to the project root.
+fn is_secret(rel: &Path, globs: &[Pattern]) -> bool {
+    let rel_str = rel.to_string_lossy();
+    let name = rel.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    globs.iter().any(|g| {
+        if g.as_str().contains('/') {
+            g.matches_with(&rel_str, MATCH_OPTIONS)
+        } else {
+            g.matches_with(&name, MATCH_OPTIONS)
+        }
+    })
+}
+
+/// Find the files and directories under `project` matching `globs`. Matched
+/// directories are masked whole, so their contents aren't listed. Only what
+/// exists at spawn time is found; files created later stay visible. Build
+/// and dependency dirs are scanned too, since the sandbox can read them.
+pub fn find_secrets(project: &Path, globs: &[String]) -> Result<Vec<String>, String> {
+    let patterns = compile_globs(globs)?;
+    if patterns.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut found = Vec::new();
+    let mut walker = WalkDir::new(project).follow_links(false).into_iter();
+    let mut seen = 0;
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        seen += 1;
+        if seen > MAX_WALK_ENTRIES {
+            return Err(format!(
+                "{} has more than {} entries to check for secrets; narrow the sandbox to a smaller project",
+                project.display(),
+                MAX_WALK_ENTRIES
+            ));
+        }
+        let Ok(rel) = entry.path().strip_prefix(project) else { continue };
+        if rel.as_os_str().is_empty() {
+            continue;
+        }
+        if is_secret(rel, &patterns) {
+            found.push(entry.path().to_string_lossy().to_string());
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn audit_log_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("audit")
+        .join("masked-secrets.jsonl")
+}
+
+/// Append what a session had masked to the audit log. Sessions that fell
+/// back to running unsandboxed are logged too, with nothing masked.
+pub fn record_masked(session_id: &str, project_dir: Option<&str>, sandboxed: bool, masked: &[String]) {
+    let entry = MaskAuditEntry {
+        session_id: session_id.to_string(),
+        project_dir: project_dir.map(String::from),
+        sandboxed,
+        masked: masked.to_vec(),
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let path = audit_log_path();
+    let result = (|| -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+    })();
+    if let Err(e) = result {
+        eprintln!("[sandbox] Failed to write audit log {}: {}", path.display(), e);
+    }
+}
+
+/// Report which paths were masked for a session, from the audit log, so it
+/// also works for terminals that have since exited.
+#[tauri::command]
+pub fn get_masked_secrets(session_id: String) -> Result<MaskAuditEntry, String> {
+    let path = audit_log_path();
+    let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<MaskAuditEntry>(&line).ok())
+        .filter(|entry| entry.session_id == session_id)
+        .last()
+        .ok_or_else(|| format!("No audit record for session: {}", session_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(rel: &str, globs: &[&str]) -> bool {
+        let globs: Vec<String> = globs.iter().map(|g| g.to_string()).collect();
+        is_secret(Path::new(rel), &compile_globs(&globs).unwrap())
+    }
+
+    #[test]
+    fn name_globs_match_at_any_depth() {
+        assert!(matches(".env", &[".env"]));
+        assert!(matches("services/api/.env", &[".env"]));
+        assert!(matches("certs/server.pem", &["*.pem"]));
+        assert!(!matches(".envrc", &[".env"]));
+        assert!(!matches("pem/readme.md", &["*.pem"]));
+    }
+
+    #[test]
+    fn env_variants_match_dot_env_star() {
+        assert!(matches(".env.local", &[".env.*"]));
+        assert!(matches("web/.env.production", &[".env.*"]));
+        assert!(!matches(".env", &[".env.*"]));
+        assert!(!matches("env.local", &[".env.*"]));
+    }
+
+    #[test]
+    fn globs_with_a_slash_are_anchored_to_the_project_root() {
+        assert!(matches("secrets/db.json", &["secrets/*.json"]));
+        assert!(matches("secrets/db.json", &["/secrets/*.json"]));
+        assert!(!matches("app/secrets/db.json", &["secrets/*.json"]));
+        assert!(!matches("secrets/nested/db.json", &["secrets/*.json"]));
+    }
+
+    #[test]
+    fn default_globs() {
+        let defaults = DEFAULT_SECRET_GLOBS;
+        assert!(matches("deploy/tls.key", defaults));
+        assert!(matches(".env.test", defaults));
+        assert!(!matches("src/main.rs", defaults));
+        assert!(!matches("config/secrets/db.json", defaults));
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Per-spawn sandbox options on top of the resolved policy
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SandboxOptions {
+    /// Mount the project through a copy-on-write overlay so changes can be
+    /// reviewed before they reach the real tree
+    pub overlay: bool,
+    /// Memory, CPU, process and open-file caps for the whole session
+    pub limits: Option<crate::sandbox::limits::ResourceLimits>,
+    /// Force a sandbox backend instead of picking the best available one
+    pub backend: Option<crate::sandbox::SandboxBackendKind>,
+}
@@ -0,0 +1,100 @@
+use crate::session_daemon::types::{DaemonRequest, DaemonResponse};
+use std::path::PathBuf;
+
+/// Unix socket the session daemon listens on
+pub fn socket_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("sessiond.sock")
+}
+
+/// Send a single request and wait for its response line
+#[cfg(unix)]
+pub fn request(request: &DaemonRequest) -> Result<DaemonResponse, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket_path())
+        .map_err(|e| format!("Session daemon not reachable: {}", e))?;
+    let json = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    writeln!(stream, "{}", json).map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read daemon response: {}", e))?;
+
+    match serde_json::from_str(&line).map_err(|e| format!("Invalid daemon response: {}", e))? {
+        DaemonResponse::Error { message } => Err(message),
+        response => Ok(response),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request(_request: &DaemonRequest) -> Result<DaemonResponse, String> {
+    Err("Persistent sessions are only supported on Unix".to_string())
+}
+
+/// Send a request that is expected to answer with a plain `Ok`
+pub fn send(req: &DaemonRequest) -> Result<(), String> {
+    match request(req)? {
+        DaemonResponse::Ok => Ok(()),
+        other => Err(format!("Unexpected daemon response: {:?}", other)),
+    }
+}
+
+#[cfg(unix)]
+pub fn is_running() -> bool {
+    std::os::unix::net::UnixStream::connect(socket_path()).is_ok()
+}
+
+#[cfg(not(unix))]
+pub fn is_running() -> bool {
+    false
+}
+
+/// Start `lirah --session-daemon` in its own process group if it isn't running yet
+#[cfg(unix)]
+pub fn ensure_daemon() -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    if is_running() {
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate Lirah executable: {}", e))?;
+    let log_path = socket_path().with_file_name("sessiond.log");
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::null());
+
+    Command::new(exe)
+        .arg("--session-daemon")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        // Detach from our process group so closing Lirah doesn't take it down
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to start session daemon: {}", e))?;
+
+    // Wait for the socket to come up
+    for _ in 0..50 {
+        if is_running() {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    Err("Session daemon did not start in time".to_string())
+}
+
+#[cfg(not(unix))]
+pub fn ensure_daemon() -> Result<(), String> {
+    Err("Persistent sessions are only supported on Unix".to_string())
+}
@@ -0,0 +1,254 @@
+use crate::pty::manager::SpawnSpec;
+use crate::pty::output::OutputFrame;
+use crate::sandbox::SandboxOptions;
+use crate::session_daemon::client;
+use crate::session_daemon::types::{DaemonRequest, DaemonResponse, DaemonSessionInfo};
+use crate::state::AppState;
+use tauri::ipc::{Channel, JavaScriptChannelId};
+use tauri::AppHandle;
+
+/// Spawn a terminal owned by the session daemon and attach to it.
+/// Called by `spawn_terminal` when the frontend asks for a persistent session.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_persistent_terminal(
+    rows: u16,
+    cols: u16,
+    sandbox: bool,
+    sandbox_no_net: bool,
+    project_dir: Option<String>,
+    spec: SpawnSpec,
+    sandbox_options: SandboxOptions,
+    on_output: Option<Channel<OutputFrame>>,
+    app: AppHandle,
+    state: &AppState,
+) -> Result<serde_json::Value, String> {
+    client::ensure_daemon()?;
+
+    let info = match client::request(&DaemonRequest::Spawn {
+        rows,
+        cols,
+        sandbox,
+        sandbox_no_net,
+        project_dir,
+        spec,
+        sandbox_options,
+    })? {
+        DaemonResponse::Spawned { session } => session,
+        other => return Err(format!("Unexpected daemon response: {:?}", other)),
+    };
+
+    attach(&info, on_output, app, state)?;
+
+    Ok(serde_json::json!({
+        "session_id": info.session_id,
+        "sandboxed": info.sandboxed,
+        "sandbox_backend": info.sandbox_backend,
+        "overlay_id": info.overlay_id,
+        "limits": info.limit_enforcement,
+        "persistent": true,
+    }))
+}
+
+/// List sessions kept alive by the daemon (empty when it isn't running)
+#[tauri::command]
+pub fn list_persistent_sessions() -> Result<Vec<DaemonSessionInfo>, String> {
+    if !client::is_running() {
+        return Ok(vec![]);
+    }
+    match client::request(&DaemonRequest::List)? {
+        DaemonResponse::Sessions { sessions } => Ok(sessions),
+        other => Err(format!("Unexpected daemon response: {:?}", other)),
+    }
+}
+
+/// Reattach to a daemon session after a restart. Returns its scrollback so the
+/// frontend can redraw before live output resumes on `on_output` (or as
+/// `terminal-output` events without a channel).
+#[tauri::command]
+pub fn attach_persistent_session(
+    session_id: String,
+    on_output: Option<JavaScriptChannelId>,
+    webview: tauri::Webview,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<serde_json::Value, String> {
+    let on_output = on_output.map(|id| id.channel_on(webview));
+    let info = list_persistent_sessions()?
+        .into_iter()
+        .find(|s| s.session_id == session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+    let scrollback = attach(&info, on_output, app, state.inner())?;
+
+    Ok(serde_json::json!({
+        "session_id": info.session_id,
+        "sandboxed": info.sandboxed,
+        "sandbox_backend": info.sandbox_backend,
+        "persistent": true,
+        "scrollback": scrollback,
+    }))
+}
+
+/// Stop streaming a daemon session into this window without killing it
+#[tauri::command]
+pub fn detach_terminal(session_id: String, state: tauri::State<AppState>) -> Result<(), String> {
+    let attachment = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .daemon_sessions
+        .remove(&session_id);
+
+    if let Some(attachment) = attachment {
+        attachment.detach();
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn attach(
+    info: &DaemonSessionInfo,
+    on_output: Option<Channel<OutputFrame>>,
+    app: AppHandle,
+    state: &AppState,
+) -> Result<String, String> {
+    use crate::pty::output::{self, OutputSink};
+    use crate::state::DaemonAttachment;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tauri::Emitter;
+
+    let mut stream = UnixStream::connect(client::socket_path())
+        .map_err(|e| format!("Session daemon not reachable: {}", e))?;
+    let request = serde_json::to_string(&DaemonRequest::Attach {
+        session_id: info.session_id.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+    writeln!(stream, "{}", request).map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|e| format!("Failed to clone stream: {}", e))?,
+    );
+
+    // First line is either the scrollback snapshot or an error
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read daemon response: {}", e))?;
+    let scrollback = match serde_json::from_str(&line)
+        .map_err(|e| format!("Invalid daemon response: {}", e))?
+    {
+        DaemonResponse::Scrollback { data } => data,
+        DaemonResponse::Error { message } => return Err(message),
+        other => return Err(format!("Unexpected daemon response: {:?}", other)),
+    };
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    if let Some(scope) = info.limit_scope.clone() {
+        crate::sandbox::limits::spawn_monitor(app.clone(), info.session_id.clone(), scope, shutdown.clone());
+    }
+    let shell = Arc::new(std::sync::Mutex::new(crate::pty::shell_integration::ShellTracker::default()));
+    // The daemon doesn't report the terminal size; the frontend's resize after
+    // attaching corrects the default
+    let screen = crate::pty::screen::new_screen(24, 80);
+    crate::pty::screen::feed(&screen, scrollback.as_bytes());
+    let last_output = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let sink = Arc::new(OutputSink::default());
+    if let Some(channel) = on_output {
+        sink.attach(channel, true);
+    }
+    let attachment = DaemonAttachment {
+        shutdown: shutdown.clone(),
+        pid: info.pid,
+        sandboxed: info.sandboxed,
+        sandbox_backend: info.sandbox_backend,
+        created_at: info.created_at,
+        shell: shell.clone(),
+        screen: screen.clone(),
+        last_output: last_output.clone(),
+        output: sink.clone(),
+        stream,
+    };
+
+    // Replacing an existing attachment (e.g. webview reload) closes the old stream
+    if let Some(previous) = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .daemon_sessions
+        .insert(info.session_id.clone(), attachment)
+    {
+        previous.detach();
+    }
+
+    let session_id = info.session_id.clone();
+    crate::pty::agent_state::watch_terminal(app.clone(), session_id.clone(), state.clone());
+    let triggers = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .triggers
+        .clone();
+    let mut matcher = crate::triggers::engine::SessionMatcher::new(&session_id);
+    let pipeline_app = app.clone();
+    let (output_tx, pipeline) = output::spawn_pipeline(app.clone(), session_id.clone(), sink.clone(), move |data| {
+        matcher.observe(&pipeline_app, &triggers, data);
+    });
+    let state = state.clone();
+    std::thread::spawn(move || {
+        for line in reader.lines() {
+            if shutdown.load(Ordering::SeqCst) {
+                return;
+            }
+            let Ok(line) = line else { break };
+            match serde_json::from_str::<DaemonResponse>(&line) {
+                Ok(DaemonResponse::Output { data }) => {
+                    crate::pty::shell_integration::observe(&app, &session_id, &shell, data.as_bytes());
+                    crate::pty::screen::feed(&screen, data.as_bytes());
+                    crate::pty::agent_state::mark_output(&last_output);
+                    if output_tx.send(data.into_bytes()).is_err() {
+                        break;
+                    }
+                }
+                Ok(DaemonResponse::Exited { exit }) => {
+                    drop(output_tx);
+                    let _ = pipeline.join();
+                    sink.send(&app, &session_id, "\r\n[Process exited]\r\n".to_string());
+                    if let Some(exit) = exit {
+                        let _ = app.emit(
+                            "terminal-exited",
+                            crate::pty::commands::TerminalExitedPayload {
+                                session_id: session_id.clone(),
+                                exit,
+                            },
+                        );
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        // Stream ended without a detach: the session or the daemon is gone
+        if !shutdown.load(Ordering::SeqCst) {
+            if let Ok(mut st) = state.lock() {
+                if st.daemon_sessions.remove(&session_id).is_some() {
+                    crate::pty::commands::cleanup_session(&mut st, &session_id);
+                }
+            }
+        }
+    });
+
+    Ok(scrollback)
+}
+
+#[cfg(not(unix))]
+fn attach(
+    _info: &DaemonSessionInfo,
+    _on_output: Option<Channel<OutputFrame>>,
+    _app: AppHandle,
+    _state: &AppState,
+) -> Result<String, String> {
+    Err("Persistent sessions are only supported on Unix".to_string())
+}
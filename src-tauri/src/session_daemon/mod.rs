@@ -0,0 +1,15 @@
+//! Detached session daemon that keeps terminals alive across app restarts.
+//!
+//! - `server`: the `lirah --session-daemon` process that owns the PTYs
+//! - `client`: socket helpers used by the app to talk to the daemon
+//! - `commands`: Tauri commands to spawn, list, attach and detach sessions
+//!
+//! The daemon listens on `~/.lirah/sessiond.sock` and speaks newline-delimited JSON.
+
+pub mod client;
+pub mod commands;
+#[cfg(unix)]
+pub mod server;
+pub mod types;
+
+pub use commands::{attach_persistent_session, detach_terminal, list_persistent_sessions};
@@ -0,0 +1,426 @@
+use crate::pty::manager;
+use crate::pty::output::Utf8Decoder;
+use crate::sandbox::secrets;
+use crate::session_daemon::client::socket_path;
+use crate::session_daemon::types::{DaemonRequest, DaemonResponse, DaemonSessionInfo};
+use crate::state::PtySession;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the daemon checks whether it has any sessions left
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Consecutive empty checks before the daemon shuts itself down
+const IDLE_CHECKS_BEFORE_EXIT: u32 = 2;
+
+/// Attached clients of one session. Kept apart from the session map so that
+/// writing to a slow client never holds up other sessions or requests.
+#[derive(Clone, Default)]
+struct Subscribers(Arc<Mutex<Vec<UnixStream>>>);
+
+impl Subscribers {
+    fn count(&self) -> usize {
+        self.0.lock().map(|s| s.len()).unwrap_or(0)
+    }
+
+    fn broadcast(&self, response: &DaemonResponse) {
+        if let Ok(mut clients) = self.0.lock() {
+            send_to(&mut clients, response);
+        }
+    }
+}
+
+/// Send a message to every attached client, dropping the ones that went away
+fn send_to(clients: &mut Vec<UnixStream>, response: &DaemonResponse) {
+    let Ok(line) = serde_json::to_string(response) else { return };
+    clients.retain_mut(|stream| writeln!(stream, "{}", line).is_ok());
+}
+
+struct DaemonSession {
+    pty: PtySession,
+    subscribers: Subscribers,
+    project_dir: Option<String>,
+    created_at: u64,
+}
+
+impl DaemonSession {
+    fn info(&self, session_id: &str) -> DaemonSessionInfo {
+        DaemonSessionInfo {
+            session_id: session_id.to_string(),
+            pid: self.pty.child.process_id(),
+            sandboxed: self.pty.sandboxed,
+            sandbox_backend: self.pty.sandbox_backend,
+            project_dir: self.project_dir.clone(),
+            overlay_id: self.pty.overlay_id.clone(),
+            limit_enforcement: self.pty.limit_enforcement,
+            limit_scope: self.pty.limit_scope.clone(),
+            created_at: self.created_at,
+            attached_clients: self.subscribers.count(),
+        }
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<String, DaemonSession>>>;
+
+/// Entry point of `lirah --session-daemon`: owns PTYs so they outlive the app
+pub fn run_daemon() -> Result<(), String> {
+    let path = socket_path();
+
+    if UnixStream::connect(&path).is_ok() {
+        return Err(format!("Session daemon already listening on {}", path.display()));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    // Stale socket left behind by a daemon that crashed
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| format!("Failed to bind {}: {}", path.display(), e))?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    eprintln!("[session-daemon] Listening on {}", path.display());
+
+    let sessions: Sessions = Arc::new(Mutex::new(HashMap::new()));
+    let connections = Arc::new(AtomicUsize::new(0));
+    spawn_idle_monitor(sessions.clone(), connections.clone());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) if peer_is_confined(&stream) => {
+                // A sandboxed terminal could otherwise ask for an unconfined
+                // shell or type into one
+                eprintln!("[session-daemon] Refusing client from inside a sandbox");
+                let mut stream = stream;
+                let response = DaemonResponse::Error {
+                    message: "Session daemon is not available inside a sandbox".to_string(),
+                };
+                if let Ok(json) = serde_json::to_string(&response) {
+                    let _ = writeln!(stream, "{}", json);
+                }
+            }
+            Ok(stream) => {
+                let sessions = sessions.clone();
+                let connection = Connection::open(&connections);
+                thread::spawn(move || {
+                    handle_client(stream, sessions);
+                    drop(connection);
+                });
+            }
+            Err(e) => eprintln!("[session-daemon] Accept failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts a client connection for as long as it's alive
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    fn open(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Connection(count.clone())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Exit once no sessions have been alive for a couple of checks, like tmux does
+fn spawn_idle_monitor(sessions: Sessions, connections: Arc<AtomicUsize>) {
+    thread::spawn(move || {
+        let mut idle_checks = 0;
+        loop {
+            thread::sleep(IDLE_CHECK_INTERVAL);
+            // Decide and exit under the lock, so a spawn that a connected
+            // client has in flight either lands first or is counted
+            let Ok(guard) = sessions.lock() else { continue };
+            let idle = guard.is_empty() && connections.load(Ordering::SeqCst) == 0;
+            idle_checks = if idle { idle_checks + 1 } else { 0 };
+            if idle_checks >= IDLE_CHECKS_BEFORE_EXIT {
+                eprintln!("[session-daemon] No sessions or clients left, shutting down");
+                let _ = std::fs::remove_file(socket_path());
+                std::process::exit(0);
+            }
+            drop(guard);
+        }
+    });
+}
+
+/// Whether the connecting process runs confined. bwrap puts it in another
+/// mount namespace and both backends set no_new_privs, which the daemon
+/// itself never has. Unknown peers count as confined.
+#[cfg(target_os = "linux")]
+fn peer_is_confined(stream: &UnixStream) -> bool {
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred and len are valid for writes and sized for SO_PEERCRED
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 || cred.pid <= 0 {
+        return true;
+    }
+
+    let peer = cred.pid.to_string();
+    let mount_ns = |pid: &str| std::fs::read_link(format!("/proc/{}/ns/mnt", pid)).ok();
+    let no_new_privs = |pid: &str| {
+        std::fs::read_to_string(format!("/proc/{}/status", pid))
+            .ok()
+            .and_then(|status| {
+                status
+                    .lines()
+                    .find_map(|l| l.strip_prefix("NoNewPrivs:").map(|v| v.trim() == "1"))
+            })
+    };
+
+    match (mount_ns(&peer), mount_ns("self")) {
+        (Some(theirs), Some(ours)) if theirs == ours => {}
+        _ => return true,
+    }
+    match (no_new_privs(&peer), no_new_privs("self")) {
+        (Some(theirs), Some(ours)) => theirs && !ours,
+        _ => true,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peer_is_confined(_stream: &UnixStream) -> bool {
+    false
+}
+
+fn handle_client(stream: UnixStream, sessions: Sessions) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(DaemonRequest::Attach { session_id }) => {
+                // The subscriber list takes over the write half from here on
+                match attach(&session_id, &writer, &sessions) {
+                    Ok(()) => continue,
+                    Err(message) => DaemonResponse::Error { message },
+                }
+            }
+            Ok(request) => dispatch(request, &sessions)
+                .unwrap_or_else(|message| DaemonResponse::Error { message }),
+            Err(e) => DaemonResponse::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        };
+
+        let Ok(json) = serde_json::to_string(&response) else { break };
+        if writeln!(writer, "{}", json).is_err() {
+            break;
+        }
+    }
+}
+
+fn dispatch(request: DaemonRequest, sessions: &Sessions) -> Result<DaemonResponse, String> {
+    match request {
+        DaemonRequest::Spawn { rows, cols, sandbox, sandbox_no_net, project_dir, spec, sandbox_options } => {
+            let pty = manager::spawn_pty(rows, cols, sandbox, sandbox_no_net, project_dir.clone(), &spec, &sandbox_options)?;
+            let masked = pty.masked_secrets.clone();
+            let info = spawn_session(pty, project_dir, sessions)?;
+            if sandbox {
+                secrets::record_masked(&info.session_id, info.project_dir.as_deref(), info.sandboxed, &masked);
+            }
+            Ok(DaemonResponse::Spawned { session: info })
+        }
+        DaemonRequest::List => {
+            let sessions = lock(sessions)?;
+            let mut list: Vec<DaemonSessionInfo> =
+                sessions.iter().map(|(id, s)| s.info(id)).collect();
+            list.sort_by_key(|s| s.created_at);
+            Ok(DaemonResponse::Sessions { sessions: list })
+        }
+        DaemonRequest::Scrollback { session_id, max_bytes } => {
+            let sessions = lock(sessions)?;
+            let session = get(&sessions, &session_id)?;
+            let bytes = session
+                .pty
+                .scrollback
+                .lock()
+                .map_err(|e| format!("Failed to lock scrollback: {}", e))?
+                .snapshot(max_bytes);
+            Ok(DaemonResponse::Scrollback {
+                data: String::from_utf8_lossy(&bytes).to_string(),
+            })
+        }
+        DaemonRequest::Write { session_id, data } => {
+            let writer = {
+                let mut sessions = lock(sessions)?;
+                get_mut(&mut sessions, &session_id)?.pty.writer.clone()
+            };
+            manager::write_to_pty(&writer, &data)?;
+            Ok(DaemonResponse::Ok)
+        }
+        DaemonRequest::Resize { session_id, rows, cols } => {
+            let mut sessions = lock(sessions)?;
+            let session = get_mut(&mut sessions, &session_id)?;
+            manager::resize_pty(&mut session.pty, rows, cols)?;
+            Ok(DaemonResponse::Ok)
+        }
+        DaemonRequest::Kill { session_id } => {
+            let removed = lock(sessions)?.remove(&session_id);
+            if let Some(mut session) = removed {
+                session.pty.shutdown.store(true, Ordering::SeqCst);
+                let _ = session.pty.child.kill();
+                let exit = manager::reap_child(session.pty.child.as_mut());
+                session.subscribers.broadcast(&DaemonResponse::Exited { exit: Some(exit) });
+            }
+            Ok(DaemonResponse::Ok)
+        }
+        DaemonRequest::Attach { .. } => Err("Attach must be handled by the connection".to_string()),
+    }
+}
+
+/// Register a client as a live subscriber. The scrollback snapshot is taken
+/// under the subscriber lock the reader thread holds while recording and
+/// sending output, so no output is lost or duplicated.
+fn attach(session_id: &str, writer: &UnixStream, sessions: &Sessions) -> Result<(), String> {
+    let (scrollback, subscribers) = {
+        let sessions = lock(sessions)?;
+        let session = get(&sessions, session_id)?;
+        (session.pty.scrollback.clone(), session.subscribers.clone())
+    };
+    let mut subscribers = subscribers
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock subscribers: {}", e))?;
+
+    let bytes = scrollback
+        .lock()
+        .map_err(|e| format!("Failed to lock scrollback: {}", e))?
+        .snapshot(None);
+
+    let mut subscriber = writer
+        .try_clone()
+        .map_err(|e| format!("Failed to clone client stream: {}", e))?;
+    // A stalled client must not block the PTY reader forever
+    let _ = subscriber.set_write_timeout(Some(Duration::from_secs(2)));
+
+    let line = serde_json::to_string(&DaemonResponse::Scrollback {
+        data: String::from_utf8_lossy(&bytes).to_string(),
+    })
+    .map_err(|e| e.to_string())?;
+    writeln!(subscriber, "{}", line).map_err(|e| format!("Failed to write to client: {}", e))?;
+
+    subscribers.push(subscriber);
+    Ok(())
+}
+
+fn spawn_session(
+    pty: PtySession,
+    project_dir: Option<String>,
+    sessions: &Sessions,
+) -> Result<DaemonSessionInfo, String> {
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    let mut reader = pty
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to clone reader: {}", e))?;
+    let shutdown_flag = pty.shutdown.clone();
+    let scrollback = pty.scrollback.clone();
+    let subscribers = Subscribers::default();
+
+    let session = DaemonSession {
+        pty,
+        subscribers: subscribers.clone(),
+        project_dir,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let info = session.info(&session_id);
+    lock(sessions)?.insert(session_id.clone(), session);
+
+    let sessions = sessions.clone();
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        // Characters split across reads are sent whole with the next read
+        let mut decoder = Utf8Decoder::default();
+        loop {
+            if shutdown_flag.load(Ordering::SeqCst) {
+                break;
+            }
+            match reader.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    // Held across record and send so `attach` sees a consistent snapshot
+                    let Ok(mut clients) = subscribers.0.lock() else { break };
+                    if let Ok(mut sb) = scrollback.lock() {
+                        sb.push(&buf[..n]);
+                    }
+                    let data = decoder.decode(&buf[..n]);
+                    if !data.is_empty() {
+                        send_to(&mut clients, &DaemonResponse::Output { data });
+                    }
+                }
+                _ => {
+                    // EOF or read error: the shell is gone. A `Kill` request
+                    // may already have removed and reaped it.
+                    let removed = sessions.lock().ok().and_then(|mut s| s.remove(&session_id));
+                    if let Some(mut session) = removed {
+                        let exit = manager::reap_child(session.pty.child.as_mut());
+                        session.subscribers.broadcast(&DaemonResponse::Exited { exit: Some(exit) });
+                    }
+                    break;
+                }
+            }
+        }
+        eprintln!("[session-daemon] Session {} ended", session_id);
+    });
+
+    Ok(info)
+}
+
+fn lock(sessions: &Sessions) -> Result<std::sync::MutexGuard<'_, HashMap<String, DaemonSession>>, String> {
+    sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock sessions: {}", e))
+}
+
+fn get<'a>(sessions: &'a HashMap<String, DaemonSession>, session_id: &str) -> Result<&'a DaemonSession, String> {
+    sessions
+        .get(session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))
+}
+
+fn get_mut<'a>(
+    sessions: &'a mut HashMap<String, DaemonSession>,
+    session_id: &str,
+) -> Result<&'a mut DaemonSession, String> {
+    sessions
+        .get_mut(session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Requests sent by Lirah to the session daemon, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    Spawn {
+        rows: u16,
+        cols: u16,
+        sandbox: bool,
+        sandbox_no_net: bool,
+        project_dir: Option<String>,
+        #[serde(default)]
+        spec: crate::pty::manager::SpawnSpec,
+        #[serde(default)]
+        sandbox_options: crate::sandbox::SandboxOptions,
+    },
+    List,
+    /// Turns the connection into a stream: a `Scrollback` response followed by
+    /// `Output` lines until the session exits or the client disconnects
+    Attach {
+        session_id: String,
+    },
+    Scrollback {
+        session_id: String,
+        max_bytes: Option<usize>,
+    },
+    Write {
+        session_id: String,
+        data: String,
+    },
+    Resize {
+        session_id: String,
+        rows: u16,
+        cols: u16,
+    },
+    Kill {
+        session_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    Ok,
+    Error { message: String },
+    Spawned { session: DaemonSessionInfo },
+    Sessions { sessions: Vec<DaemonSessionInfo> },
+    Scrollback { data: String },
+    Output { data: String },
+    /// Always set by this daemon, including on kill; optional so that
+    /// messages from an older daemon still parse
+    Exited { exit: Option<crate::pty::manager::ExitInfo> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonSessionInfo {
+    pub session_id: String,
+    pub pid: Option<u32>,
+    pub sandboxed: bool,
+    pub project_dir: Option<String>,
+    #[serde(default)]
+    pub sandbox_backend: Option<crate::sandbox::SandboxBackendKind>,
+    #[serde(default)]
+    pub overlay_id: Option<String>,
+    #[serde(default)]
+    pub limit_enforcement: crate::sandbox::limits::Enforcement,
+    #[serde(default)]
+    pub limit_scope: Option<String>,
+    pub created_at: u64,
+    pub attached_clients: usize,
+}
@@ -4,12 +4,61 @@ use std::io::Write;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
+pub type PtyWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
 pub struct PtySession {
     pub master: Box<dyn MasterPty + Send>,
     pub child: Box<dyn Child + Send>,
-    pub writer: Box<dyn Write + Send>,
+    /// Shared so large writes can run without holding the state lock
+    pub writer: PtyWriter,
     pub shutdown: Arc<AtomicBool>,
     pub sandboxed: bool,
+    /// Which sandbox confines the session, `None` when unsandboxed
+    pub sandbox_backend: Option<crate::sandbox::SandboxBackendKind>,
+    pub scrollback: Arc<Mutex<crate::pty::scrollback::ScrollbackBuffer>>,
+    pub recorder: Arc<Mutex<Option<crate::pty::recording::CastRecorder>>>,
+    pub started_at: std::time::Instant,
+    /// Copy-on-write overlay the project is mounted through, if any
+    pub overlay_id: Option<String>,
+    /// Secret files and directories hidden from the sandbox at spawn time
+    pub masked_secrets: Vec<String>,
+    /// How the session's resource limits are enforced, if it has any
+    pub limit_enforcement: crate::sandbox::limits::Enforcement,
+    /// systemd scope unit holding the session when limits use a cgroup
+    pub limit_scope: Option<String>,
+    /// Cwd and command history reported by shell integration
+    pub shell: Arc<Mutex<crate::pty::shell_integration::ShellTracker>>,
+    /// Emulated screen contents, for reading what the terminal shows
+    pub screen: crate::pty::screen::ScreenModel,
+    /// Unix timestamp (milliseconds) of the last output, for agent state detection
+    pub last_output: Arc<std::sync::atomic::AtomicU64>,
+    /// Where batched output goes: the frontend's channel or the global event
+    pub output: Arc<crate::pty::output::OutputSink>,
+}
+
+/// A terminal owned by the session daemon that this app is streaming from
+pub struct DaemonAttachment {
+    pub shutdown: Arc<AtomicBool>,
+    pub pid: Option<u32>,
+    pub sandboxed: bool,
+    pub sandbox_backend: Option<crate::sandbox::SandboxBackendKind>,
+    /// Unix timestamp (seconds) at which the daemon spawned the session
+    pub created_at: u64,
+    pub shell: Arc<Mutex<crate::pty::shell_integration::ShellTracker>>,
+    pub screen: crate::pty::screen::ScreenModel,
+    pub last_output: Arc<std::sync::atomic::AtomicU64>,
+    pub output: Arc<crate::pty::output::OutputSink>,
+    #[cfg(unix)]
+    pub stream: std::os::unix::net::UnixStream,
+}
+
+impl DaemonAttachment {
+    /// Stop the output thread; the session keeps running in the daemon
+    pub fn detach(&self) {
+        self.shutdown.store(true, std::sync::atomic::Ordering::SeqCst);
+        #[cfg(unix)]
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
 }
 
 pub struct WorkspaceContext {
@@ -19,6 +68,25 @@ pub struct WorkspaceContext {
 
 pub struct AppStateData {
     pub pty_sessions: HashMap<String, PtySession>,
+    pub daemon_sessions: HashMap<String, DaemonAttachment>,
+    /// Cancel flags of running asciicast replays, keyed by replay id
+    pub replays: HashMap<String, Arc<AtomicBool>>,
+    /// Background jobs whose process is still running, keyed by job id
+    pub jobs: HashMap<String, crate::jobs::manager::RunningJob>,
+    /// Output trigger rules, shared with every terminal's reader thread
+    pub triggers: crate::triggers::engine::TriggerStore,
+    /// Detected agent state per terminal that has run Claude Code or opencode
+    pub agent_states: HashMap<String, crate::pty::agent_state::AgentStatus>,
+    /// Prompts waiting to be pasted into a terminal's agent, persisted
+    pub prompt_queues: crate::pty::prompt_queue::PromptQueues,
+    /// Named sets of terminals that receive the same input, keyed by group id
+    pub broadcast_groups: crate::pty::broadcast::BroadcastGroups,
+    /// Terminals shared read-only over the local web mirror
+    pub mirror: crate::mirror::types::MirrorState,
+    /// Terminals running ssh against a saved host, keyed by session id
+    pub remote_sessions: HashMap<String, crate::remote::types::RemoteSession>,
+    /// AI generations in progress, so they can be cancelled
+    pub generations: crate::llm::generation::Generations,
     pub git_cache: crate::git_cache::GitStatsCache,
     pub directory_cache: crate::directory_cache::DirectoryCache,
     pub workspace_context: Option<WorkspaceContext>,
@@ -29,6 +97,16 @@ pub type AppState = Arc<Mutex<AppStateData>>;
 pub fn create_state() -> AppState {
     Arc::new(Mutex::new(AppStateData {
         pty_sessions: HashMap::new(),
+        daemon_sessions: HashMap::new(),
+        replays: HashMap::new(),
+        jobs: HashMap::new(),
+        triggers: crate::triggers::engine::create_trigger_store(),
+        agent_states: HashMap::new(),
+        prompt_queues: crate::pty::prompt_queue::load_queues(),
+        broadcast_groups: HashMap::new(),
+        mirror: Default::default(),
+        remote_sessions: HashMap::new(),
+        generations: HashMap::new(),
         git_cache: crate::git_cache::GitStatsCache::new(),
         directory_cache: crate::directory_cache::DirectoryCache::new(),
         workspace_context: None,
@@ -0,0 +1,56 @@
+use crate::state::AppState;
+use crate::triggers::types::TriggerRule;
+
+/// Configured trigger rules. With `session_id`, only the global rules and
+/// those scoped to that terminal.
+#[tauri::command]
+pub fn list_trigger_rules(
+    session_id: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<TriggerRule>, String> {
+    let store = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .triggers
+        .clone();
+    let rules = store
+        .lock()
+        .map_err(|e| format!("Failed to lock triggers: {}", e))?
+        .rules();
+    Ok(match session_id {
+        Some(id) => rules
+            .into_iter()
+            .filter(|r| r.session_id.as_ref().is_none_or(|s| *s == id))
+            .collect(),
+        None => rules,
+    })
+}
+
+/// Create a rule (empty `id`) or replace an existing one. Returns the saved
+/// rule with its id.
+#[tauri::command]
+pub fn save_trigger_rule(rule: TriggerRule, state: tauri::State<AppState>) -> Result<TriggerRule, String> {
+    let store = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .triggers
+        .clone();
+    let mut set = store
+        .lock()
+        .map_err(|e| format!("Failed to lock triggers: {}", e))?;
+    set.save(rule)
+}
+
+/// Delete a rule. Returns false if there was none with that id.
+#[tauri::command]
+pub fn remove_trigger_rule(id: String, state: tauri::State<AppState>) -> Result<bool, String> {
+    let store = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .triggers
+        .clone();
+    let mut set = store
+        .lock()
+        .map_err(|e| format!("Failed to lock triggers: {}", e))?;
+    set.remove(&id)
+}
@@ -0,0 +1,229 @@
+use crate::triggers::types::{TriggerConfig, TriggerHit, TriggerRule};
+use regex::{Regex, RegexBuilder};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Longest unterminated line kept for matching; TUIs that never print a
+/// newline would otherwise grow it forever
+const MAX_LINE_BYTES: usize = 4096;
+
+/// Upper bound on a compiled pattern, so one rule can't stall every reader
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+pub type TriggerStore = Arc<Mutex<TriggerSet>>;
+
+struct CompiledRule {
+    rule: TriggerRule,
+    regex: Regex,
+}
+
+/// The configured rules, compiled
+#[derive(Default)]
+pub struct TriggerSet {
+    rules: Vec<CompiledRule>,
+}
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".lirah")
+        .join("triggers.json")
+}
+
+fn compile(rule: &TriggerRule) -> Result<Regex, String> {
+    RegexBuilder::new(&rule.pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("Invalid trigger pattern: {}", e))
+}
+
+/// Load the rules from disk. Rules that no longer compile are skipped.
+pub fn create_trigger_store() -> TriggerStore {
+    let config: TriggerConfig = match std::fs::read_to_string(config_path()) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("[triggers] Ignoring invalid {}: {}", config_path().display(), e);
+            TriggerConfig::default()
+        }),
+        Err(_) => TriggerConfig::default(),
+    };
+    let rules = config
+        .rules
+        .into_iter()
+        .filter_map(|rule| match compile(&rule) {
+            Ok(regex) => Some(CompiledRule { rule, regex }),
+            Err(e) => {
+                eprintln!("[triggers] Skipping rule {}: {}", rule.id, e);
+                None
+            }
+        })
+        .collect();
+    Arc::new(Mutex::new(TriggerSet { rules }))
+}
+
+impl TriggerSet {
+    pub fn rules(&self) -> Vec<TriggerRule> {
+        self.rules.iter().map(|c| c.rule.clone()).collect()
+    }
+
+    /// Add a rule, or replace the one with the same id
+    pub fn save(&mut self, mut rule: TriggerRule) -> Result<TriggerRule, String> {
+        let regex = compile(&rule)?;
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        let compiled = CompiledRule { rule: rule.clone(), regex };
+        match self.rules.iter_mut().find(|c| c.rule.id == rule.id) {
+            Some(existing) => *existing = compiled,
+            None => self.rules.push(compiled),
+        }
+        self.persist()?;
+        Ok(rule)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<bool, String> {
+        let before = self.rules.len();
+        self.rules.retain(|c| c.rule.id != id);
+        if self.rules.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    /// Drop the rules scoped to a terminal that has been closed
+    pub fn remove_session(&mut self, session_id: &str) {
+        let before = self.rules.len();
+        self.rules.retain(|c| c.rule.session_id.as_deref() != Some(session_id));
+        if self.rules.len() != before {
+            if let Err(e) = self.persist() {
+                eprintln!("[triggers] {}", e);
+            }
+        }
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let config = TriggerConfig { rules: self.rules() };
+        let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Remove escape sequences and control characters other than tab
+pub fn strip_ansi(text: &str) -> std::borrow::Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let re = ANSI.get_or_init(|| {
+        Regex::new(concat!(
+            r"\x1b\[[0-?]*[ -/]*[@-~]",        // CSI
+            r"|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)", // OSC
+            r"|\x1b[PX^_][^\x1b]*\x1b\\",      // DCS, SOS, PM, APC
+            r"|\x1b[()][0-9A-Za-z]",           // charset selection
+            r"|\x1b[@-Z\\-_]",                 // other two-byte escapes
+            r"|[\x00-\x08\x0b-\x1f\x7f]",
+        ))
+        .expect("ANSI pattern is valid")
+    });
+    re.replace_all(text, "")
+}
+
+/// Per-terminal matching state, owned by the terminal's reader thread
+pub struct SessionMatcher {
+    session_id: String,
+    /// Output since the last newline
+    partial: String,
+    /// Rules already reported for `partial`, so a prompt that is still being
+    /// drawn doesn't fire again when its line completes
+    fired_on_partial: HashSet<String>,
+    last_fired: HashMap<String, Instant>,
+}
+
+impl SessionMatcher {
+    pub fn new(session_id: &str) -> Self {
+        SessionMatcher {
+            session_id: session_id.to_string(),
+            partial: String::new(),
+            fired_on_partial: HashSet::new(),
+            last_fired: HashMap::new(),
+        }
+    }
+
+    /// Match a chunk of output and emit `terminal-trigger` for each hit
+    pub fn observe(&mut self, app: &AppHandle, store: &TriggerStore, data: &str) {
+        for hit in self.feed(store, data) {
+            let _ = app.emit("terminal-trigger", &hit);
+        }
+    }
+
+    fn feed(&mut self, store: &TriggerStore, data: &str) -> Vec<TriggerHit> {
+        let Ok(set) = store.lock() else { return vec![] };
+        let rules: Vec<&CompiledRule> = set
+            .rules
+            .iter()
+            .filter(|c| c.rule.enabled)
+            .filter(|c| c.rule.session_id.as_ref().is_none_or(|s| *s == self.session_id))
+            .collect();
+        if rules.is_empty() {
+            self.partial.clear();
+            self.fired_on_partial.clear();
+            return vec![];
+        }
+
+        let mut hits = Vec::new();
+        self.partial.push_str(data);
+        while let Some(pos) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=pos).collect();
+            self.check(&rules, &line, true, &mut hits);
+            self.fired_on_partial.clear();
+        }
+        if self.partial.len() > MAX_LINE_BYTES {
+            let mut cut = self.partial.len() - MAX_LINE_BYTES;
+            while !self.partial.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.partial.drain(..cut);
+        }
+        if !self.partial.is_empty() {
+            let partial = self.partial.clone();
+            self.check(&rules, &partial, false, &mut hits);
+        }
+        hits
+    }
+
+    fn check(&mut self, rules: &[&CompiledRule], line: &str, complete: bool, hits: &mut Vec<TriggerHit>) {
+        let mut stripped = None;
+        for compiled in rules {
+            let rule = &compiled.rule;
+            if self.fired_on_partial.contains(&rule.id) {
+                continue;
+            }
+            let text: &str = if rule.strip_ansi {
+                stripped.get_or_insert_with(|| strip_ansi(line).to_string())
+            } else {
+                line
+            };
+            let Some(m) = compiled.regex.find(text) else { continue };
+            if !complete {
+                self.fired_on_partial.insert(rule.id.clone());
+            }
+            let cooldown = Duration::from_millis(rule.cooldown_ms);
+            if self.last_fired.get(&rule.id).is_some_and(|t| t.elapsed() < cooldown) {
+                continue;
+            }
+            self.last_fired.insert(rule.id.clone(), Instant::now());
+            hits.push(TriggerHit {
+                session_id: self.session_id.clone(),
+                rule_id: rule.id.clone(),
+                rule_name: rule.name.clone(),
+                matched: m.as_str().to_string(),
+                line: text.trim_end().to_string(),
+            });
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Output triggers: regex rules matched against terminal output that raise a
+//! `terminal-trigger` event, e.g. for permission prompts or failing tests in
+//! terminals nobody is looking at.
+//!
+//! - `types`: rule, config file and event payload
+//! - `engine`: compiled rule set and the per-terminal line matcher
+//! - `commands`: Tauri commands to list, save and remove rules
+//!
+//! Rules are stored in `~/.lirah/triggers.json`.
+
+pub mod commands;
+pub mod engine;
+pub mod types;
+
+pub use commands::{list_trigger_rules, remove_trigger_rule, save_trigger_rule};
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cooldown_ms() -> u64 {
+    2000
+}
+
+/// A regex watched for in terminal output
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TriggerRule {
+    /// Assigned when the rule is first saved
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub pattern: String,
+    /// Only watch this terminal; `None` applies to every terminal
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Match against the output with escape sequences removed
+    #[serde(default = "default_true")]
+    pub strip_ansi: bool,
+    /// Minimum time between two hits of this rule in the same terminal
+    #[serde(default = "default_cooldown_ms")]
+    pub cooldown_ms: u64,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// `~/.lirah/triggers.json`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TriggerConfig {
+    #[serde(default)]
+    pub rules: Vec<TriggerRule>,
+}
+
+/// Payload of `terminal-trigger`
+#[derive(Serialize, Clone, Debug)]
+pub struct TriggerHit {
+    pub session_id: String,
+    pub rule_id: String,
+    pub rule_name: Option<String>,
+    /// The text the regex matched
+    pub matched: String,
+    /// The whole line it was found in
+    pub line: String,
+}
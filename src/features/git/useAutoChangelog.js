@@ -63,21 +63,20 @@ export function useAutoChangelog(currentPath, enabled, targetFile = "CHANGELOG.m
         const command = cli === 'opencode'
           ? `opencode run -m opencode/kimi-k2.5-free '${escaped}'`
           : `claude -p '${escaped}' --allowedTools 'Bash(git diff:*),Bash(git log:*),Read,Write'`;
-        const sessionId = await invoke("spawn_hidden_terminal", {
-          projectDir: repo_path,
-          command,
+        const job = await invoke("submit_job", {
+          spec: { command, cwd: repo_path, label: "changelog", timeout_secs: 600 },
         });
 
-        // Listen for this hidden terminal to close
-        const unlistenClose = await listen("hidden-terminal-closed", (closeEvent) => {
-          if (closeEvent.payload.session_id === sessionId) {
-            setStatus(closeEvent.payload.error ? "error" : "done");
+        // Listen for this job to finish
+        const unlistenFinished = await listen("job-finished", (finishedEvent) => {
+          if (finishedEvent.payload.id === job.id) {
+            setStatus(finishedEvent.payload.status === "succeeded" ? "done" : "error");
             dismissTimer.current = setTimeout(() => setStatus(null), 4000);
-            unlistenClose();
+            unlistenFinished();
           }
         });
       } catch (err) {
-        console.error("Failed to spawn hidden terminal for changelog:", err);
+        console.error("Failed to start changelog job:", err);
         setStatus("error");
         dismissTimer.current = setTimeout(() => setStatus(null), 4000);
       }
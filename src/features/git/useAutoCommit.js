@@ -1,5 +1,6 @@
 import { useState, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { runGeneration, cancelGeneration } from "../../utils/generation";
 
 export function useAutoCommit(cli = 'claude-code', customPrompt = '') {
   const [stage, setStage] = useState('idle'); // idle | loading-files | generating-message | ready | committing | done | error
@@ -9,6 +10,7 @@ export function useAutoCommit(cli = 'claude-code', customPrompt = '') {
   const [error, setError] = useState(null);
   const currentPathRef = useRef(null);
   const cancelledRef = useRef(false);
+  const jobIdRef = useRef(null);
 
   const reset = useCallback(() => {
     setStage('idle');
@@ -51,17 +53,21 @@ export function useAutoCommit(cli = 'claude-code', customPrompt = '') {
       // Generate commit message via backend (which gets diff via git command)
       setStage('generating-message');
 
-      const msg = await invoke('generate_commit_message', {
+      const msg = await runGeneration('generate_commit_message', {
         projectDir: currentPath,
         cli,
         customPrompt: customPrompt.trim() || null,
+      }, {
+        onStart: (jobId) => { jobIdRef.current = jobId; },
       });
+      jobIdRef.current = null;
       if (cancelledRef.current) return;
 
       const lines = msg.split('\n').filter(l => l.trim().length > 0);
       setCommitMessage(lines.length > 0 ? lines.join('\n') : 'chore: update files');
       setStage('ready');
     } catch (err) {
+      jobIdRef.current = null;
       if (cancelledRef.current) return;
       setError(err.toString());
       setStage('error');
@@ -84,6 +90,8 @@ export function useAutoCommit(cli = 'claude-code', customPrompt = '') {
 
   const cancel = useCallback(async () => {
     cancelledRef.current = true;
+    cancelGeneration(jobIdRef.current);
+    jobIdRef.current = null;
     const repoPath = currentPathRef.current;
     if (repoPath && (stage === 'ready' || stage === 'generating-message')) {
       try {
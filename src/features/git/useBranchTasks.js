@@ -1,6 +1,7 @@
 import { useState, useCallback, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { runGeneration } from "../../utils/generation";
 
 const STORAGE_KEY = 'nevo-terminal:branch-tasks';
 
@@ -93,7 +94,7 @@ export function useBranchTasks(cli = 'claude-code') {
     try {
       setStage('generating');
       
-      const result = await invoke('generate_branch_tasks', {
+      const result = await runGeneration('generate_branch_tasks', {
         projectDir: currentPath,
         baseBranch: baseBranchName,
         currentBranch: currentBranchName,
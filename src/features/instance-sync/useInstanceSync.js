@@ -119,7 +119,7 @@ export function useInstanceSync(currentPath, selectedFiles, claudeSessionId) {
           current_focus: '', // Could be set via UI
           active_files: selectedFiles || [],
           claude_session_id: claudeSessionId,
-          status: 'active', // lowercase to match Rust enum
+          // status is computed by the backend's agent state detector
         };
         
         const state = await invoke('update_instance_state', { update });
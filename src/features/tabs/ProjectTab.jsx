@@ -24,6 +24,7 @@ import { useTokenUsage } from "../token-budget";
 import { useTypeChecker } from "../../hooks/useTypeChecker";
 import { usePromptSender } from "../../hooks/usePromptSender";
 import { escapeShellPath, getRelativePath } from "../../utils/pathUtils";
+import { runGeneration } from "../../utils/generation";
 import { useOrchestrationCheck } from "../../hooks/useOrchestrationCheck";
 import { TokenBudgetProvider } from "../token-budget";
 import { SecondaryTerminal } from "../../components/SecondaryTerminal";
@@ -344,7 +345,7 @@ function ProjectTabInner({ projectPath, isActive, tabId }) {
       .map(msg => ({ role: msg.role, content: msg.content }));
     if (selectedMessageObjects.length === 0) return;
     try {
-      const generatedPrompt = await invoke('generate_instance_sync_prompt', {
+      const generatedPrompt = await runGeneration('generate_instance_sync_prompt', {
         projectDir: currentPath,
         cli: settings.selectedCli,
         promptType,
@@ -129,7 +129,8 @@ export function useFlatViewNavigation(terminalSessionId) {
       }
 
       // Now load files from the confirmed directory
-      const directories = await invoke('read_directory', { path: targetPath });
+      // The session id lets the backend list ssh terminals' directories over SFTP
+      const directories = await invoke('read_directory', { path: targetPath, sessionId: terminalSessionId });
       console.log('Loaded', directories.length, 'items from:', targetPath);
 
       // Merge in deleted files from git stats
@@ -168,24 +168,13 @@ export function usePromptSender({
         }
       }
 
-      // Send text content first
-      await invoke('write_to_terminal', {
+      // Paste in chunks and submit; very large prompts go to a file the agent reads
+      await invoke('paste_to_terminal', {
         sessionId: terminalSessionId,
-        data: fullCommand
+        text: fullCommand,
+        options: { submit: true, spill_over_bytes: 100 * 1024 }
       });
 
-      // Small delay then send Enter (carriage return) to submit
-      setTimeout(async () => {
-        try {
-          await invoke('write_to_terminal', {
-            sessionId: terminalSessionId,
-            data: '\r'
-          });
-        } catch (error) {
-          console.error('Failed to send Enter:', error);
-        }
-      }, 500);
-
       console.log('Sent to terminal:', fullCommand);
 
       // Focus terminal
@@ -2,8 +2,7 @@ import { useState, useEffect, useCallback, useImperativeHandle, useRef } from 'r
 import { Terminal } from '@xterm/xterm';
 import { FitAddon } from '@xterm/addon-fit';
 import { WebLinksAddon } from '@xterm/addon-web-links';
-import { invoke } from '@tauri-apps/api/core';
-import { listen } from '@tauri-apps/api/event';
+import { invoke, Channel } from '@tauri-apps/api/core';
 import { useToast } from '../features/toast';
 import '@xterm/xterm/css/xterm.css';
 
@@ -100,7 +99,24 @@ export function useTerminal(terminalRef, theme, imperativeRef, onSearchFocus, on
   useEffect(() => {
     if (!terminal || !fitAddon) return;
 
-    let unlisten;
+    let disposed = false;
+
+    // Output arrives in batched frames on a per-terminal channel. Rendered
+    // bytes are acknowledged so the backend pauses when xterm falls behind.
+    const ACK_BYTES = 256 * 1024;
+    let rendered = 0;
+    const onOutput = new Channel();
+    onOutput.onmessage = (frame) => {
+      if (disposed) return;
+      terminal.write(frame.data, () => {
+        rendered += frame.bytes;
+        const id = sessionIdRef.current;
+        if (id && rendered >= ACK_BYTES) {
+          invoke('ack_terminal_output', { sessionId: id, bytes: rendered }).catch(() => {});
+          rendered = 0;
+        }
+      });
+    };
 
     const initTerminal = async () => {
       try {
@@ -109,7 +125,7 @@ export function useTerminal(terminalRef, theme, imperativeRef, onSearchFocus, on
         const cols = terminal.cols;
 
         // Spawn terminal backend
-        const result = await invoke('spawn_terminal', { rows, cols, sandbox: sandboxEnabled, sandboxNoNet: networkIsolation, projectDir: projectDir || null });
+        const result = await invoke('spawn_terminal', { rows, cols, sandbox: sandboxEnabled, sandboxNoNet: networkIsolation, projectDir: projectDir || null, onOutput });
         const id = result.session_id;
         sessionIdRef.current = id;
         setSessionId(id);
@@ -129,13 +145,6 @@ export function useTerminal(terminalRef, theme, imperativeRef, onSearchFocus, on
           });
         }
 
-        // Listen for terminal output
-        unlisten = await listen('terminal-output', (event) => {
-          if (event.payload.session_id === id) {
-            terminal.write(event.payload.data);
-          }
-        });
-
         // Handle terminal input
         terminal.onData((data) => {
           invoke('write_to_terminal', { sessionId: id, data }).catch((error) => {
@@ -167,6 +176,10 @@ export function useTerminal(terminalRef, theme, imperativeRef, onSearchFocus, on
         }
       } catch (err) {
         console.error('Failed to initialize terminal:', err);
+        // Sandboxed spawns fail instead of silently running unconfined
+        if (sandboxEnabled) {
+          setSandboxFailed(true);
+        }
         const errorMessage = err?.message || err?.toString() || 'Unknown error';
         terminal.write(`\r\n\x1b[1;31mError: ${errorMessage}\x1b[0m\r\n`);
         error(`Failed to initialize terminal: ${errorMessage}`, {
@@ -185,9 +198,7 @@ export function useTerminal(terminalRef, theme, imperativeRef, onSearchFocus, on
     initTerminal();
 
     return () => {
-      if (unlisten) {
-        unlisten();
-      }
+      disposed = true;
       const id = sessionIdRef.current;
       if (id) {
         sessionIdRef.current = null;
@@ -57,7 +57,7 @@ export function useTreeView({ terminalSessionId, setCurrentPath, initializeSearc
         projectRootRef.current = cwd;
       }
       const allEntries = await invoke('read_directory_recursive', {
-        path: cwd, maxDepth: 10, maxFiles: 10000
+        path: cwd, maxDepth: 10, maxFiles: 10000, sessionId: terminalSessionId
       });
       const treeNodes = buildTreeFromFlatList(allEntries, cwd);
       setTreeData(treeNodes);
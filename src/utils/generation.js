@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+/**
+ * Run one of the generate_* commands and wait for its result.
+ *
+ * The backend returns a job id straight away and reports the outcome through
+ * `generation-finished`; partial output arrives as `generation-progress`.
+ * Listeners are registered before the command is invoked so a fast job can't
+ * finish unseen.
+ *
+ * @param {string} command - e.g. 'generate_commit_message'
+ * @param {object} args - command arguments
+ * @param {object} [options]
+ * @param {(chunk: string, payload: object) => void} [options.onProgress]
+ * @param {(jobId: string) => void} [options.onStart] - receives the id for cancel_generation
+ * @returns {Promise<any>} the job's result; rejects with its error or 'Cancelled'
+ */
+export async function runGeneration(command, args, { onProgress, onStart } = {}) {
+  let jobId = null;
+  const early = [];
+  let settle = null;
+
+  const unlistenFinished = await listen('generation-finished', (event) => {
+    if (jobId === null) {
+      early.push(event.payload);
+    } else if (event.payload.job_id === jobId) {
+      settle?.(event.payload);
+    }
+  });
+  const unlistenProgress = onProgress
+    ? await listen('generation-progress', (event) => {
+        if (event.payload.job_id === jobId) onProgress(event.payload.chunk, event.payload);
+      })
+    : null;
+
+  try {
+    const payload = await new Promise((resolve, reject) => {
+      settle = resolve;
+      invoke(command, args)
+        .then((id) => {
+          jobId = id;
+          onStart?.(id);
+          const done = early.find(p => p.job_id === id);
+          if (done) resolve(done);
+        })
+        .catch(reject);
+    });
+    if (payload.status === 'succeeded') return payload.result;
+    throw payload.status === 'cancelled' ? 'Cancelled' : (payload.error || 'Generation failed');
+  } finally {
+    unlistenFinished();
+    unlistenProgress?.();
+  }
+}
+
+export function cancelGeneration(jobId) {
+  if (!jobId) return Promise.resolve(false);
+  return invoke('cancel_generation', { jobId }).catch(() => false);
+}